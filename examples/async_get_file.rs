@@ -0,0 +1,67 @@
+use anyhow::{bail, Error};
+use libmtp_rs::device::raw::detect_raw_devices;
+use libmtp_rs::device::StorageSort;
+use libmtp_rs::storage::asynchronous::AsyncStorage;
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll, Wake};
+use std::thread::{self, Thread};
+
+/// Waker that unparks the thread blocked in `block_on`.
+struct ThreadWaker(Thread);
+
+impl Wake for ThreadWaker {
+    fn wake(self: Arc<Self>) {
+        self.0.unpark();
+    }
+}
+
+/// Minimal executor to run the example without depending on an async runtime, any runtime can
+/// be used instead.
+fn block_on<F: Future>(future: F) -> F::Output {
+    let mut future = Box::pin(future);
+    let waker = Arc::new(ThreadWaker(thread::current())).into();
+    let mut cx = Context::from_waker(&waker);
+
+    loop {
+        match Pin::new(&mut future).poll(&mut cx) {
+            Poll::Ready(output) => return output,
+            Poll::Pending => thread::park(),
+        }
+    }
+}
+
+fn main() -> Result<(), Error> {
+    let args: Vec<_> = std::env::args().collect();
+    let (id, dest) = match args.as_slice() {
+        [_, id, dest] => (id.parse::<u32>()?, dest.clone()),
+        _ => bail!("Usage: async_get_file <object id> <local file>"),
+    };
+
+    let raw_devices = detect_raw_devices()?;
+    let raw = match raw_devices.first() {
+        Some(raw) => raw,
+        None => bail!("No devices"),
+    };
+
+    let mut mtp_device = raw.try_open_uncached()?;
+    mtp_device.update_storage(StorageSort::ByFreeSpace)?;
+
+    // Objects have unique ids across all the device, so any storage works to get a file.
+    let storage = AsyncStorage::new(mtp_device, 0);
+    let (mut progress, transfer) = storage.get_file_to_path_with_progress(id, dest);
+
+    block_on(async {
+        // The transfer runs in the background, the progress ends once it's done.
+        while let Some((sent, total)) = progress.recv().await {
+            println!("Progress {}/{}", sent, total);
+        }
+
+        transfer.await
+    })?;
+
+    println!("Done");
+    Ok(())
+}
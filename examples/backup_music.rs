@@ -0,0 +1,162 @@
+use anyhow::{bail, Error};
+use libmtp_rs::device::raw::detect_raw_devices;
+use libmtp_rs::device::StorageSort;
+use libmtp_rs::object::filetypes::Filetype;
+use libmtp_rs::object::Object;
+use libmtp_rs::storage::albums::Album;
+use libmtp_rs::storage::files::File;
+use libmtp_rs::storage::folders::sanitize_folder_name;
+use libmtp_rs::storage::tracks::Track;
+use libmtp_rs::storage::{Parent, StoragePool};
+
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// Tracks of an album, together with their metadata (if any).
+type AlbumTracks<'a> = Vec<(File<'a>, Option<Track<'a>>)>;
+
+/// Local name of an artist or album directory, missing names (and names that would escape the
+/// destination) go to "Unknown".
+fn dir_name(name: Option<&str>) -> String {
+    match name.map(sanitize_folder_name) {
+        Some(name) if !matches!(name.as_str(), "" | "." | "..") => name,
+        _ => "Unknown".to_string(),
+    }
+}
+
+/// Writes the cover art of the album containing any of the tracks as `cover.<ext>`, if any.
+fn save_cover_art(albums: &[Album], tracks: &AlbumTracks, album_dir: &Path) -> Result<(), Error> {
+    let album = albums.iter().find(|album| {
+        tracks
+            .iter()
+            .any(|(file, _)| album.tracks().contains(&file.id()))
+    });
+
+    let sample = match album {
+        Some(album) => album.cover_art()?,
+        None => None,
+    };
+
+    if let Some(sample) = sample {
+        let ext = sample.filetype.extension().unwrap_or("bin");
+        std::fs::write(album_dir.join(format!("cover.{}", ext)), &sample.data)?;
+    }
+
+    Ok(())
+}
+
+/// Collects (recursively) every audio file in the device.
+fn audio_files<'a>(pool: &StoragePool<'a>, parent: Parent, files: &mut Vec<File<'a>>) {
+    for file in pool.files_and_folders(parent) {
+        match file.ftype() {
+            Filetype::Folder => audio_files(pool, Parent::Folder(file.id()), files),
+
+            ftype if ftype.is_audio() => files.push(file),
+
+            _ => continue,
+        }
+    }
+}
+
+fn main() -> Result<(), Error> {
+    let dest = match std::env::args().nth(1) {
+        Some(dest) => PathBuf::from(dest),
+        None => bail!("Usage: backup_music <local directory>"),
+    };
+
+    let raw_devices = detect_raw_devices()?;
+    let mtp_device = if let Some(raw) = raw_devices.first() {
+        raw.try_open_uncached().ok()
+    } else {
+        println!("No devices");
+        return Ok(());
+    };
+
+    let mut mtp_device = match mtp_device {
+        Some(mtp_device) => mtp_device,
+        None => {
+            println!("Couldn't open device");
+            return Ok(());
+        }
+    };
+
+    mtp_device.update_storage(StorageSort::ByFreeSpace)?;
+    let storage_pool = mtp_device.storage_pool();
+
+    let mut files = Vec::new();
+    audio_files(&storage_pool, Parent::Root, &mut files);
+
+    // Group the tracks by artist and album, files without track metadata go to "Unknown".
+    let mut albums: BTreeMap<(String, String), AlbumTracks> = BTreeMap::new();
+    for file in files {
+        let track = Track::from_id(&mtp_device, &file).ok();
+        let artist = dir_name(track.as_ref().and_then(|t| t.artist()));
+        let album = dir_name(track.as_ref().and_then(|t| t.album()));
+
+        albums
+            .entry((artist, album))
+            .or_default()
+            .push((file, track));
+    }
+
+    let album_list = storage_pool.album_list();
+    let total: usize = albums.values().map(Vec::len).sum();
+    let mut done = 0;
+    let mut failures = Vec::new();
+
+    for ((artist, album), tracks) in &albums {
+        let album_dir = dest.join(artist).join(album);
+        std::fs::create_dir_all(&album_dir)?;
+
+        // Keep the metadata of the tracks next to them.
+        let mut index = std::fs::File::create(album_dir.join("tracks.txt"))?;
+
+        for (file, track) in tracks {
+            done += 1;
+            println!("[{}/{}] {}/{}/{}", done, total, artist, album, file.name());
+
+            let name = sanitize_folder_name(&file.name());
+            if matches!(name.as_str(), "" | "." | "..") {
+                failures.push((
+                    file.name().to_string(),
+                    "invalid local file name".to_string(),
+                ));
+                continue;
+            }
+
+            let local = album_dir.join(name);
+            if let Err(err) = storage_pool.get_file_to_path(file, &local) {
+                failures.push((file.name().to_string(), err.to_string()));
+                continue;
+            }
+
+            match track {
+                Some(track) => writeln!(
+                    index,
+                    "{}\t{}\t{}\t{} ms",
+                    file.name(),
+                    track.title().unwrap_or(""),
+                    track.artist().unwrap_or(""),
+                    track.duration()
+                )?,
+
+                None => writeln!(index, "{}", file.name())?,
+            }
+        }
+
+        if let Err(err) = save_cover_art(&album_list, tracks, &album_dir) {
+            println!(
+                "Couldn't get the cover art of {}/{}: {}",
+                artist, album, err
+            );
+        }
+    }
+
+    println!("Backed up {} of {} tracks", total - failures.len(), total);
+    for (name, err) in &failures {
+        println!("Failed {}: {}", name, err);
+    }
+
+    Ok(())
+}
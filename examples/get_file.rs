@@ -11,7 +11,7 @@ use text_io::read;
 fn main() -> Result<(), Error> {
     let raw_devices = detect_raw_devices()?;
     let mtp_device = if let Some(raw) = raw_devices.get(0) {
-        raw.open_uncached()
+        raw.try_open_uncached().ok()
     } else {
         println!("No devices");
         return Ok(());
@@ -6,23 +6,25 @@ fn main() -> Result<(), Error> {
     let mtp_devices = raw_devices
         .into_iter()
         .inspect(|raw| println!("Found a device with an mtp descriptor:\n{:#?}", raw))
-        .map(|raw| raw.open_uncached());
+        .map(|raw| raw.try_open_uncached());
 
     for (i, mtp_device) in mtp_devices.enumerate() {
-        if let Some(mtp_device) = mtp_device {
-            let name = if let Ok(fname) = mtp_device.get_friendly_name() {
-                fname
-            } else {
-                format!(
-                    "{} {}",
-                    mtp_device.manufacturer_name()?,
-                    mtp_device.model_name()?
-                )
-            };
+        match mtp_device {
+            Ok(mtp_device) => {
+                let name = if let Ok(fname) = mtp_device.get_friendly_name() {
+                    fname
+                } else {
+                    format!(
+                        "{} {}",
+                        mtp_device.manufacturer_name()?,
+                        mtp_device.model_name()?
+                    )
+                };
 
-            println!("Device {}: {}", i + 1, name);
-        } else {
-            println!("Couldn't open device {}", i + 1);
+                println!("Device {}: {}", i + 1, name);
+            }
+
+            Err(err) => println!("Couldn't open device {}: {}", i + 1, err),
         }
     }
 
@@ -4,16 +4,13 @@ use libmtp_rs::device::StorageSort;
 use libmtp_rs::object::filetypes::Filetype;
 use libmtp_rs::object::Object;
 use libmtp_rs::storage::files::File;
-use libmtp_rs::storage::folders::Folder;
+use libmtp_rs::storage::folders::FolderNode;
 use libmtp_rs::storage::{Parent, Storage};
 
-fn print_folder_tree_wfolder(folder: Option<Folder>, level: usize) {
-    if let Some(folder) = folder {
-        println!("{:>level$}{}", "", folder.name(), level = level);
-        print_folder_tree_wfolder(folder.child(), level + 1);
-        while let Some(sibling) = folder.sibling() {
-            print_folder_tree_wfolder(Some(sibling), level);
-        }
+fn print_folder_tree_wnodes(nodes: &[FolderNode], level: usize) {
+    for node in nodes {
+        println!("{:>level$}{}", "", node.name, level = level);
+        print_folder_tree_wnodes(&node.children, level + 1);
     }
 }
 
@@ -33,7 +30,9 @@ fn print_folder_tree_wfiles(storage: &Storage, files: Vec<File>, level: usize) {
 
 fn main() -> Result<(), Error> {
     let raw_devices = detect_raw_devices()?;
-    let mtp_devices = raw_devices.into_iter().map(|raw| raw.open_uncached());
+    let mtp_devices = raw_devices
+        .into_iter()
+        .map(|raw| raw.try_open_uncached().ok());
 
     for (idx, mtp_device) in mtp_devices.enumerate() {
         if let Some(mut mtp_device) = mtp_device {
@@ -42,13 +41,12 @@ fn main() -> Result<(), Error> {
             let (_, storage) = storage_pool.iter().next().expect("No storage");
 
             println!("{:#?}", storage);
+            println!("/");
 
-            let root = storage.folder_list();
-            if let Some(root) = root {
-                print_folder_tree_wfolder(Some(root), 0);
+            if let Some(root) = storage.folder_tree() {
+                print_folder_tree_wnodes(&root.children, 1);
             } else {
                 let root_contents = storage.files_and_folders(Parent::Root);
-                println!("/");
                 print_folder_tree_wfiles(storage, root_contents, 1);
             }
         } else {
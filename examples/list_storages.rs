@@ -4,7 +4,9 @@ use libmtp_rs::device::StorageSort;
 
 fn main() -> Result<(), Error> {
     let raw_devices = detect_raw_devices()?;
-    let mtp_devices = raw_devices.into_iter().map(|raw| raw.open_uncached());
+    let mtp_devices = raw_devices
+        .into_iter()
+        .map(|raw| raw.try_open_uncached().ok());
 
     for (i, mtp_device) in mtp_devices.enumerate() {
         if let Some(mut mtp_device) = mtp_device {
@@ -15,7 +17,7 @@ fn main() -> Result<(), Error> {
                 println!("Storage {}:", i + 1);
                 println!(
                     "  Description: {}",
-                    storage.description().unwrap_or_else(|| "Unknown")
+                    storage.description().as_deref().unwrap_or("Unknown")
                 );
                 println!(
                     "  Max. capacity: {}",
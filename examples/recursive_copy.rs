@@ -0,0 +1,105 @@
+use anyhow::{bail, Error};
+use libmtp_rs::device::raw::detect_raw_devices;
+use libmtp_rs::device::StorageSort;
+use libmtp_rs::object::filetypes::Filetype;
+use libmtp_rs::object::Object;
+use libmtp_rs::storage::files::File;
+use libmtp_rs::storage::folders::sanitize_folder_name;
+use libmtp_rs::storage::{Parent, StoragePool};
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+/// Folder id of a parent, `None` being the root.
+fn folder_id(parent: Parent) -> Option<u32> {
+    match parent {
+        Parent::Root => None,
+        Parent::Folder(id) => Some(id),
+    }
+}
+
+fn copy_file(pool: &StoragePool, file: &File, dest: &Path) -> Result<(), Error> {
+    println!("{}", dest.display());
+    pool.get_file_to_path_create_dirs(file, dest)?;
+
+    // Preserve the modification time of the file on the device.
+    let mtime =
+        UNIX_EPOCH + Duration::from_secs(file.modification_date().timestamp().max(0) as u64);
+    std::fs::File::options()
+        .write(true)
+        .open(dest)?
+        .set_modified(mtime)?;
+
+    Ok(())
+}
+
+/// Copies every file under `parent` keeping the folder structure, note that empty folders
+/// aren't created.
+fn copy_folder(pool: &StoragePool, parent: Parent, dest: &Path) -> Result<usize, Error> {
+    // Folders are walked before their contents, so the local path of the parent of each file is
+    // already known.
+    let mut paths = HashMap::new();
+    paths.insert(folder_id(parent), dest.to_path_buf());
+
+    let mut copied = 0;
+    for file in pool.walk(parent) {
+        let name = sanitize_folder_name(&file.name());
+        let local = match paths.get(&folder_id(file.parent_id())) {
+            Some(_) if matches!(name.as_str(), "" | "." | "..") => {
+                println!("Skipping {:?}, invalid local name", file.name());
+                continue;
+            }
+
+            Some(parent) => parent.join(name),
+            None => continue,
+        };
+
+        match file.ftype() {
+            Filetype::Folder => {
+                paths.insert(Some(file.id()), local);
+            }
+
+            _ => {
+                copy_file(pool, &file, &local)?;
+                copied += 1;
+            }
+        }
+    }
+
+    Ok(copied)
+}
+
+fn main() -> Result<(), Error> {
+    let mut args = std::env::args().skip(1);
+    let (source, dest) = match (args.next(), args.next()) {
+        (Some(source), Some(dest)) => (source, PathBuf::from(dest)),
+        _ => bail!("Usage: recursive_copy <device folder, e.g. DCIM> <local directory>"),
+    };
+
+    let raw_devices = detect_raw_devices()?;
+    let mtp_device = if let Some(raw) = raw_devices.first() {
+        raw.try_open_uncached().ok()
+    } else {
+        println!("No devices");
+        return Ok(());
+    };
+
+    if let Some(mut mtp_device) = mtp_device {
+        mtp_device.update_storage(StorageSort::ByFreeSpace)?;
+
+        let storage_pool = mtp_device.storage_pool();
+        match storage_pool.folder_by_path(&source) {
+            Some(parent) => {
+                let copied = copy_folder(&storage_pool, parent, &dest)?;
+                println!("Copied {} files to {}", copied, dest.display());
+            }
+
+            None => println!("Couldn't find {} in any storage", source),
+        }
+    } else {
+        println!("Couldn't open device");
+    }
+
+    Ok(())
+}
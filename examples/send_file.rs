@@ -16,7 +16,7 @@ fn main() -> Result<(), Error> {
 
     let raw_devices = detect_raw_devices()?;
     let mtp_device = if let Some(raw) = raw_devices.get(0) {
-        raw.open_uncached()
+        raw.try_open_uncached().ok()
     } else {
         println!("No devices");
         return Ok(());
@@ -35,7 +35,11 @@ fn main() -> Result<(), Error> {
         let metadata = FileMetadata {
             file_size: metadata.len(),
             file_name: path.file_name().unwrap().to_str().expect("Invalid UTF-8"),
-            file_type: Filetype::Text,
+            file_type: path
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .and_then(Filetype::from_extension)
+                .unwrap_or(Filetype::Unknown),
             modification_date: metadata.modified()?.into(),
         };
 
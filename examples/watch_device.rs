@@ -0,0 +1,94 @@
+use anyhow::Error;
+use libmtp_rs::device::raw::{detect_raw_devices, RawDevice};
+use libmtp_rs::error::{Error as MtpError, MtpErrorKind};
+
+use std::collections::HashMap;
+use std::thread;
+use std::time::Duration;
+
+/// Change reported by the `DeviceWatcher`.
+enum DeviceChange {
+    Connected(RawDevice),
+    Disconnected(String),
+}
+
+/// Polls the connected devices and reports which ones were connected or disconnected since the
+/// last poll, devices are told apart by their persistent id.
+struct DeviceWatcher {
+    known: HashMap<String, String>,
+}
+
+impl DeviceWatcher {
+    fn new() -> Self {
+        DeviceWatcher {
+            known: HashMap::new(),
+        }
+    }
+
+    fn poll(&mut self) -> Result<Vec<DeviceChange>, Error> {
+        let devices = match detect_raw_devices() {
+            Ok(devices) => devices,
+            Err(MtpError::MtpError {
+                kind: MtpErrorKind::NoDeviceAttached,
+                ..
+            }) => Vec::new(),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut changes = Vec::new();
+        let mut current = HashMap::new();
+
+        for raw in devices {
+            let id = raw.persistent_id();
+            let entry = raw.device_entry();
+            current.insert(id.clone(), format!("{} {}", entry.vendor, entry.product));
+
+            if !self.known.contains_key(&id) {
+                changes.push(DeviceChange::Connected(raw));
+            }
+        }
+
+        for (id, label) in &self.known {
+            if !current.contains_key(id) {
+                changes.push(DeviceChange::Disconnected(label.clone()));
+            }
+        }
+
+        self.known = current;
+        Ok(changes)
+    }
+}
+
+fn main() -> Result<(), Error> {
+    let mut watcher = DeviceWatcher::new();
+    println!("Watching devices, press Ctrl+C to stop");
+
+    loop {
+        for change in watcher.poll()? {
+            match change {
+                DeviceChange::Connected(raw) => {
+                    let entry = raw.device_entry();
+                    println!(
+                        "connected: {} {} ({})",
+                        entry.vendor,
+                        entry.product,
+                        raw.location_string()
+                    );
+
+                    match raw.try_open_uncached() {
+                        Ok(device) => match device.get_friendly_name() {
+                            Ok(name) => println!("  friendly name: {}", name),
+                            Err(err) => println!("  no friendly name: {}", err),
+                        },
+
+                        Err(err) => println!("  couldn't open the device: {}", err),
+                    }
+                }
+
+                DeviceChange::Disconnected(label) => println!("disconnected: {}", label),
+            }
+        }
+
+        thread::sleep(Duration::from_secs(1));
+    }
+}
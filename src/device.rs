@@ -3,24 +3,35 @@
 //! to be able to send or get files, folders, tracks, etc.
 
 pub mod capabilities;
+pub mod events;
 pub mod raw;
 
-use capabilities::DeviceCapability;
+use capabilities::{DeviceCapability, Operation};
+use events::{EventReceiver, MtpEvent};
 use libmtp_sys as ffi;
 use num_derive::ToPrimitive;
 use num_traits::{FromPrimitive, ToPrimitive};
+use raw::RawDevice;
+use std::cell::{Cell, RefCell};
 use std::ffi::CString;
 use std::fmt::{self, Debug};
+use std::mem::MaybeUninit;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
 
-use crate::error::Error;
+use crate::error::{Error, MtpErrorKind};
 use crate::object::filetypes::Filetype;
 use crate::object::properties::Property;
+use crate::object::samples::SampleFormat;
 use crate::object::{AsObjectId, DummyObject};
 use crate::storage::files::File;
 use crate::storage::StoragePool;
-use crate::values::AllowedValues;
+use crate::values::{AllowedValues, DataType};
 use crate::Result;
 
+/// Maximum number of parameters of a PTP operation, see `MtpDevice::custom_operation`.
+pub const MAX_CUSTOM_OPERATION_PARAMS: usize = 5;
+
 /// Sorting logic to apply after the update of storages.
 #[derive(Debug, Clone, Copy, ToPrimitive)]
 pub enum StorageSort {
@@ -50,12 +61,18 @@ pub enum UpdateResult {
 /// Information about the battery level gather from a device with
 /// [`MtpDevice::battery_level`](struct.MtpDevice.html#method.battery_level).
 ///
+/// Note that devices report a current level of `0` both when they are on external power and
+/// when their battery is (almost) empty, e.g. charging from empty, `libmtp` doesn't expose any
+/// charging state to tell these apart. Because of this a `0` is only treated as external power
+/// if the device doesn't report a maximum level either, otherwise the level is `Unknown`.
+///
 /// ## Example
 /// ```no_run
 /// let (level, max_level) = mtp_device.battery_level().expect("Failed to get battery level");
 /// match level {
 ///     BatteryLevel::OnBattery(level) => println!("Using battery, current level {}", level),
 ///     BatteryLevel::OnExternalPower => println!("Using external power, connected to AC"),
+///     BatteryLevel::Unknown => println!("Either on external power or the battery is empty"),
 /// }
 /// ```
 #[derive(Debug, Copy, Clone)]
@@ -64,6 +81,96 @@ pub enum BatteryLevel {
     OnBattery(u8),
     /// The device is currently on external power.
     OnExternalPower,
+    /// The device reported a current level of `0` but a non-zero maximum level, it may be on
+    /// external power or its battery may be empty.
+    Unknown,
+}
+
+impl BatteryLevel {
+    pub(crate) fn from_levels(current: u8, max: u8) -> Self {
+        match (current, max) {
+            (0, 0) => BatteryLevel::OnExternalPower,
+            (0, _) => BatteryLevel::Unknown,
+            (current, _) => BatteryLevel::OnBattery(current),
+        }
+    }
+}
+
+/// Charging state of the battery of a device, see `Battery`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BatteryState {
+    /// The device is running on battery.
+    Discharging,
+    /// The battery is charging, note that `libmtp` doesn't report this yet, so it's never
+    /// inferred by [`MtpDevice::battery`](struct.MtpDevice.html#method.battery).
+    Charging,
+    /// The device is running on external power (it reported no battery levels at all).
+    External,
+    /// The state couldn't be inferred (e.g. a current level of `0` with a non-zero maximum).
+    Unknown,
+}
+
+/// Battery status of a device, retrieved with
+/// [`MtpDevice::battery`](struct.MtpDevice.html#method.battery).
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Battery {
+    pub current: u8,
+    pub max: u8,
+    /// Current level as a percentage of the maximum level, `None` if the device didn't report
+    /// a maximum level or the state is `Unknown` (a `0` might not be an empty battery).
+    pub percentage: Option<u8>,
+    pub state: BatteryState,
+}
+
+impl Battery {
+    pub(crate) fn from_levels(current: u8, max: u8) -> Self {
+        let state = match (current, max) {
+            (0, 0) => BatteryState::External,
+            (0, _) => BatteryState::Unknown,
+            (_, _) => BatteryState::Discharging,
+        };
+
+        let percentage = if max > 0 && state != BatteryState::Unknown {
+            Some((current.min(max) as u32 * 100 / max as u32) as u8)
+        } else {
+            None
+        };
+
+        Battery {
+            current,
+            max,
+            percentage,
+            state,
+        }
+    }
+}
+
+/// Identification of a device gathered at once with
+/// [`MtpDevice::info`](struct.MtpDevice.html#method.info), strings that the device doesn't
+/// report are `None`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeviceInfo {
+    pub manufacturer: Option<String>,
+    pub model: Option<String>,
+    pub serial: Option<String>,
+    pub friendly_name: Option<String>,
+    pub vendor_id: u16,
+    pub product_id: u16,
+}
+
+/// Process-unique identifier of an opened `MtpDevice`, it's assigned when the device is opened
+/// and never reused while the process is alive (even if the same device is opened again).
+///
+/// Useful to tell apart objects from different devices, see
+/// [`Object::device_id`](../object/trait.Object.html#method.device_id).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct DeviceId(u64);
+
+impl DeviceId {
+    fn next() -> Self {
+        static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+        DeviceId(NEXT_ID.fetch_add(1, Ordering::Relaxed))
+    }
 }
 
 /// Result from opening a raw device descriptor, holds information about the device like
@@ -73,6 +180,15 @@ pub enum BatteryLevel {
 /// which you may get with [`storage_pool`](struct.MtpDevice.html#method.storage_pool) after
 /// updating the storage with [`update_storage`](struct.MtpDevice.html#method.update_storage).
 ///
+/// ## Threads
+/// `MtpDevice` is `Send` but not `Sync`: it can be moved to another thread (e.g. a worker
+/// thread that performs the transfers), but it can't be shared between threads, since `libmtp`
+/// doesn't synchronize requests to the same device. To use it from several threads share it
+/// behind an `Arc<Mutex<MtpDevice>>`, like
+/// [`event_thread`](struct.MtpDevice.html#method.event_thread) does to read events in the
+/// background. Storages, files and other objects borrow the device, so they stay in the thread
+/// that owns it (or holds its lock).
+///
 /// ## Example
 /// ```no_run
 /// mtp_device.update_storage().expect("Couldn't update storage");
@@ -80,8 +196,20 @@ pub enum BatteryLevel {
 /// ```
 pub struct MtpDevice {
     pub(crate) inner: *mut ffi::LIBMTP_mtpdevice_t,
+    raw: RawDevice,
+    id: DeviceId,
+    remove_failed_sends: Cell<bool>,
+    friendly_name: RefCell<Option<String>>,
+    supported_filetypes: RefCell<Option<Vec<Filetype>>>,
+    error_stack: RefCell<Vec<Error>>,
 }
 
+// Safety: `libmtp` keeps the whole state of a device in its `LIBMTP_mtpdevice_t` (there's no
+// thread-local state, and libusb handles can be used from any thread), so the device can be moved
+// to another thread. It's still not `Sync` (because of its `Cell`s), thus it's never used by two
+// threads at the same time.
+unsafe impl Send for MtpDevice {}
+
 impl Drop for MtpDevice {
     fn drop(&mut self) {
         unsafe {
@@ -109,17 +237,94 @@ impl Debug for MtpDevice {
 }
 
 impl MtpDevice {
+    pub(crate) fn from_raw(inner: *mut ffi::LIBMTP_mtpdevice_t, raw: RawDevice) -> Self {
+        MtpDevice {
+            inner,
+            raw,
+            id: DeviceId::next(),
+            remove_failed_sends: Cell::new(false),
+            friendly_name: RefCell::new(None),
+            supported_filetypes: RefCell::new(None),
+            error_stack: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Calls a string getter of `libmtp` and converts its result (freeing it), a null string is
+    /// `Ok(None)` unless `libmtp` reported an error. The error stack is cleared first, so a stale
+    /// error isn't mistaken for a failure of the getter.
+    unsafe fn optional_string(
+        &self,
+        getter: unsafe extern "C" fn(*mut ffi::LIBMTP_mtpdevice_t) -> *mut libc::c_char,
+    ) -> Result<Option<String>> {
+        self.clear_errors();
+        let string = getter(self.inner);
+
+        if string.is_null() {
+            match self.latest_error() {
+                Some(err) => Err(err),
+                None => Ok(None),
+            }
+        } else {
+            let u8vec = cstr_to_u8vec!(string);
+            libc::free(string as *mut _);
+            Ok(Some(String::from_utf8(u8vec)?))
+        }
+    }
+
+    /// Takes the latest error of the error stack, the whole stack is kept to be returned by
+    /// `error_stack` and then cleared.
     pub(crate) fn latest_error(&self) -> Option<Error> {
+        let stack = unsafe { Error::from_error_stack(ffi::LIBMTP_Get_Errorstack(self.inner)) };
+        let err = stack.last().cloned()?;
+
+        self.clear_errors();
+        *self.error_stack.borrow_mut() = stack;
+        Some(err)
+    }
+
+    /// Clears the error stack, so errors left by earlier requests aren't mistaken for errors of
+    /// the next one (needed when failure is only detected through the error stack).
+    pub(crate) fn clear_errors(&self) {
         unsafe {
-            let list = ffi::LIBMTP_Get_Errorstack(self.inner);
-            let err = Error::from_latest_error(list)?;
             ffi::LIBMTP_Clear_Errorstack(self.inner);
-            Some(err)
         }
     }
 }
 
 impl MtpDevice {
+    /// Returns every error (from the oldest to the latest) reported by `libmtp` for the last
+    /// failed operation, the `Error` returned by that operation is only the latest one and the
+    /// previous ones often explain the real cause. If there are errors not reported yet they're
+    /// returned instead.
+    pub fn error_stack(&self) -> Vec<Error> {
+        let pending = unsafe { Error::from_error_stack(ffi::LIBMTP_Get_Errorstack(self.inner)) };
+
+        if pending.is_empty() {
+            self.error_stack.borrow().clone()
+        } else {
+            pending
+        }
+    }
+
+    /// Returns the process-unique identifier assigned to this device when it was opened.
+    pub fn device_id(&self) -> DeviceId {
+        self.id
+    }
+
+    /// Whether objects left by failed sends will be removed, see
+    /// [`set_remove_failed_sends`](struct.MtpDevice.html#method.set_remove_failed_sends).
+    pub fn remove_failed_sends(&self) -> bool {
+        self.remove_failed_sends.get()
+    }
+
+    /// If a send (e.g. `Storage::send_file_from_path`) fails in the middle of the transfer (like
+    /// when the storage is full), the device may keep an incomplete object wasting space. Setting
+    /// this to `true` makes every send on this device delete that object before returning the
+    /// error, by default this is disabled.
+    pub fn set_remove_failed_sends(&self, remove: bool) {
+        self.remove_failed_sends.set(remove);
+    }
+
     /// Retrieves the default music folder, if there isn't one this value may be garbage.
     /// Therefore, it's not recommended to depend on this value, unless you know exactly
     /// how the device you are interacting with handles this setting.
@@ -176,31 +381,52 @@ impl MtpDevice {
         unsafe { (*self.inner).default_text_folder }
     }
 
-    /// Gets the friendly name of this device, e.g. "Kevin's Android"
+    /// Gets the friendly name of this device, e.g. "Kevin's Android", the fetched name is also
+    /// cached (see [`cached_friendly_name`](struct.MtpDevice.html#method.cached_friendly_name)).
     pub fn get_friendly_name(&self) -> Result<String> {
-        unsafe {
+        let name = unsafe {
             let friendly_name = ffi::LIBMTP_Get_Friendlyname(self.inner);
 
             if friendly_name.is_null() {
-                Err(self.latest_error().unwrap_or_default())
+                return Err(self.latest_error().unwrap_or_default());
             } else {
                 let u8vec = cstr_to_u8vec!(friendly_name);
                 libc::free(friendly_name as *mut _);
-                Ok(String::from_utf8(u8vec)?)
+                String::from_utf8(u8vec)?
             }
-        }
+        };
+
+        self.friendly_name.replace(Some(name.clone()));
+        Ok(name)
+    }
+
+    /// Returns the friendly name cached by the latest call to `get_friendly_name` or
+    /// `set_friendly_name`, this doesn't perform any I/O so the name may be outdated.
+    pub fn cached_friendly_name(&self) -> Option<String> {
+        self.friendly_name.borrow().clone()
+    }
+
+    /// Fetches the friendly name from the device and compares it against the cached one (e.g.
+    /// if another host changed it), the cache is updated with the fetched name. Returns whether
+    /// the name changed, if there wasn't a cached name this returns `false`.
+    pub fn friendly_name_changed(&self) -> Result<bool> {
+        let cached = self.cached_friendly_name();
+        let current = self.get_friendly_name()?;
+
+        Ok(cached.is_some_and(|cached| cached != current))
     }
 
     /// Sets the friendly name of this device
     pub fn set_friendly_name(&self, name: &str) -> Result<()> {
-        let name = CString::new(name).expect("Nul byte");
+        let cname = CString::new(name)?;
 
         unsafe {
-            let res = ffi::LIBMTP_Set_Friendlyname(self.inner, name.as_ptr());
+            let res = ffi::LIBMTP_Set_Friendlyname(self.inner, cname.as_ptr());
 
             if res != 0 {
                 Err(self.latest_error().unwrap_or_default())
             } else {
+                self.friendly_name.replace(Some(name.to_string()));
                 Ok(())
             }
         }
@@ -218,7 +444,7 @@ impl MtpDevice {
 
     /// Sets the synchronization partner of this device.
     pub fn set_sync_partner(&self, partner: &str) -> Result<()> {
-        let partner = CString::new(partner).expect("Nul byte");
+        let partner = CString::new(partner)?;
 
         unsafe {
             let res = ffi::LIBMTP_Set_Syncpartner(self.inner, partner.as_ptr());
@@ -261,6 +487,26 @@ impl MtpDevice {
         }
     }
 
+    /// Returns the identification of this device (manufacturer, model, serial number, friendly
+    /// name, vendor id and product id) at once, see `DeviceInfo`.
+    pub fn info(&self) -> Result<DeviceInfo> {
+        let entry = &self.raw.inner.device_entry;
+
+        let info = unsafe {
+            DeviceInfo {
+                manufacturer: self.optional_string(ffi::LIBMTP_Get_Manufacturername)?,
+                model: self.optional_string(ffi::LIBMTP_Get_Modelname)?,
+                serial: self.optional_string(ffi::LIBMTP_Get_Serialnumber)?,
+                friendly_name: self.optional_string(ffi::LIBMTP_Get_Friendlyname)?,
+                vendor_id: entry.vendor_id,
+                product_id: entry.product_id,
+            }
+        };
+
+        self.friendly_name.replace(info.friendly_name.clone());
+        Ok(info)
+    }
+
     /// Returns the serial number of this device.
     pub fn serial_number(&self) -> Result<String> {
         unsafe {
@@ -292,7 +538,36 @@ impl MtpDevice {
         }
     }
 
+    /// Retrieves the battery status of this device, see `Battery`.
+    pub fn battery(&self) -> Result<Battery> {
+        let mut max_level = 0;
+        let mut cur_level = 0;
+
+        let res =
+            unsafe { ffi::LIBMTP_Get_Batterylevel(self.inner, &mut max_level, &mut cur_level) };
+
+        if res != 0 {
+            Err(self.latest_error().unwrap_or_default())
+        } else {
+            Ok(Battery::from_levels(cur_level, max_level))
+        }
+    }
+
+    /// Retrieves the battery level of this device as a percentage of its maximum level, returns
+    /// `None` if the device is on external power, doesn't report a maximum level or reports a
+    /// current level of `0` (see `BatteryState::Unknown`).
+    pub fn battery_percentage(&self) -> Result<Option<u8>> {
+        Ok(self.battery()?.percentage)
+    }
+
+    /// Returns the maximum battery level this device reported when it was opened, `0` if it
+    /// doesn't report one (e.g. devices without battery).
+    pub fn maximum_battery_level(&self) -> u8 {
+        unsafe { (*self.inner).maximum_battery_level }
+    }
+
     /// Retrieves the current and maximum battery level of this device.
+    #[deprecated(note = "use `battery` instead, it also reports the charging state")]
     pub fn battery_level(&self) -> Result<(BatteryLevel, u8)> {
         unsafe {
             let mut max_level = 0;
@@ -303,13 +578,7 @@ impl MtpDevice {
             if res != 0 {
                 Err(self.latest_error().unwrap_or_default())
             } else {
-                let cur_level = if cur_level == 0 {
-                    BatteryLevel::OnExternalPower
-                } else {
-                    BatteryLevel::OnBattery(cur_level)
-                };
-
-                Ok((cur_level, max_level))
+                Ok((BatteryLevel::from_levels(cur_level, max_level), max_level))
             }
         }
     }
@@ -349,11 +618,101 @@ impl MtpDevice {
                 }
 
                 libc::free(filetypes as *mut _);
+                self.supported_filetypes
+                    .replace(Some(filetypes_vec.clone()));
                 Ok(filetypes_vec)
             }
         }
     }
 
+    /// Checks whether this device supports the given filetype, the list of supported filetypes
+    /// is retrieved once (see [`supported_filetypes`](#method.supported_filetypes)) and reused
+    /// by later checks.
+    pub fn supports_filetype(&self, filetype: &Filetype) -> Result<bool> {
+        self.ensure_supported_filetypes()?;
+
+        let supported = self.supported_filetypes.borrow();
+        Ok(supported.iter().flatten().any(|ftype| ftype == filetype))
+    }
+
+    /// Same as [`supports_filetype`](#method.supports_filetype) but for multiple filetypes at
+    /// once, returns each queried filetype together with whether it's supported, in order.
+    /// This is useful to pick a target format out of several candidates.
+    pub fn supports_filetypes(&self, types: &[Filetype]) -> Result<Vec<(Filetype, bool)>> {
+        self.ensure_supported_filetypes()?;
+
+        let supported = self.supported_filetypes.borrow();
+        let supported = supported.as_deref().unwrap_or_default();
+
+        Ok(types
+            .iter()
+            .map(|ftype| (ftype.clone(), supported.contains(ftype)))
+            .collect())
+    }
+
+    /// Retrieves the supported filetypes if they weren't cached yet.
+    fn ensure_supported_filetypes(&self) -> Result<()> {
+        if self.supported_filetypes.borrow().is_none() {
+            self.supported_filetypes()?;
+        }
+
+        Ok(())
+    }
+
+    /// Retrieves the requirements of the representative samples (e.g. cover art) for objects of
+    /// the given filetype, returns `None` if the device doesn't support samples for it.
+    pub fn sample_format_requirements(&self, filetype: Filetype) -> Result<Option<SampleFormat>> {
+        let filetype = filetype.to_u32().expect("Unexpected variant in Filetype");
+        let mut sample = std::ptr::null_mut();
+
+        let res = unsafe {
+            ffi::LIBMTP_Get_Representative_Sample_Format(self.inner, filetype, &mut sample)
+        };
+
+        if res != 0 {
+            Err(self.latest_error().unwrap_or_default())
+        } else if sample.is_null() {
+            Ok(None)
+        } else {
+            unsafe {
+                let format = SampleFormat::from_raw(sample);
+                ffi::LIBMTP_destroy_filesampledata_t(sample);
+                Ok(Some(format))
+            }
+        }
+    }
+
+    /// Waits for the next event of this device (e.g. an object was added), see `MtpEvent`.
+    ///
+    /// **Note:** This call **blocks** until the device reports an event, so you may want to
+    /// call it from its own thread, keep in mind that other requests to the device shouldn't
+    /// happen at the same time.
+    pub fn read_event(&self) -> Result<MtpEvent> {
+        let mut event = ffi::LIBMTP_event_enum_LIBMTP_EVENT_NONE;
+        let mut param = 0;
+
+        let res = unsafe { ffi::LIBMTP_Read_Event(self.inner, &mut event, &mut param) };
+
+        if res != 0 {
+            Err(self.latest_error().unwrap_or_default())
+        } else {
+            Ok(MtpEvent::from_raw(event, param))
+        }
+    }
+
+    /// Spawns a background thread that reads the events of the given device (see
+    /// [`read_event`](#method.read_event)) and forwards them to a channel, that way the events
+    /// can be polled from an event loop instead of blocking, see `EventReceiver` for how the
+    /// thread is stopped.
+    ///
+    /// The device is shared with the thread behind a mutex, so requests are never made at the
+    /// same time as the thread handles events. While waiting for an event the thread only holds
+    /// the lock for a tenth of a second at a time, thus other requests can still be made by
+    /// locking the device, they may just wait up to that long to start.
+    pub fn event_thread(device: &Arc<Mutex<MtpDevice>>) -> EventReceiver {
+        events::event_thread(Arc::clone(device))
+    }
+
     /// Check whether this device has some specific capabilitiy.
     pub fn check_capability(&self, capability: DeviceCapability) -> bool {
         unsafe {
@@ -363,6 +722,16 @@ impl MtpDevice {
         }
     }
 
+    /// Check whether this device supports some specific operation. Note that `libmtp` doesn't
+    /// expose the full list of operations of a device, only the ones behind a `DeviceCapability`
+    /// can be checked (partial reads/writes, edits, moves and copies), for any other operation
+    /// this returns `None`.
+    pub fn supports_operation(&self, operation: Operation) -> Option<bool> {
+        operation
+            .capability()
+            .map(|capability| self.check_capability(capability))
+    }
+
     /// Reset the device only if this one supports the `PTP_OC_ResetDevice` operation code
     /// (`0x1010`)
     pub fn reset_device(&self) -> Result<()> {
@@ -377,6 +746,15 @@ impl MtpDevice {
         }
     }
 
+    /// Releases this device (closing the handle, as dropping it would) and returns the raw
+    /// device it was opened from, so it can be reopened later with `RawDevice::try_open` or
+    /// `RawDevice::try_open_uncached`.
+    pub fn release(self) -> RawDevice {
+        let raw = self.raw.duplicate();
+        drop(self);
+        raw
+    }
+
     /// Updates all the internal storage ids and properties of this device, it can also
     /// optionally sort the list. This operation may success, partially success
     /// (only ids were retrieved) or fail.
@@ -400,6 +778,22 @@ impl MtpDevice {
         }
     }
 
+    /// Returns the largest capacity among the storages of this device (the storage must be
+    /// updated with `update_storage` first), `None` if there's no storage or no capacity was
+    /// reported.
+    ///
+    /// Note that this isn't the maximum size of an object, `libmtp` doesn't expose per-object
+    /// limits (e.g. 4 GiB on players formatted as FAT32), so a smaller object may still be
+    /// rejected by the device. Sends check the size of the file against the capacity of the
+    /// target storage, returning `Error::ObjectTooLarge` before transferring anything.
+    pub fn max_storage_capacity(&self) -> Option<u64> {
+        self.storage_pool()
+            .iter()
+            .map(|(_, storage)| storage.maximum_capacity())
+            .max()
+            .filter(|&capacity| capacity > 0)
+    }
+
     /// Dumps out a large chunk of textual information provided from the PTP protocol and
     /// additionally some extra MTP specific information where applicable.
     pub fn dump_device_info(&self) {
@@ -429,12 +823,25 @@ impl MtpDevice {
         property: Property,
         filetype: Filetype,
     ) -> Result<AllowedValues> {
+        self.maybe_allowed_property_values(property, filetype)?
+            .ok_or(Error::Unknown)
+    }
+
+    /// Same as `allowed_property_values`, but returns `Ok(None)` if the device doesn't describe
+    /// the allowed values of the property (`libmtp` fails without reporting any error then).
+    fn maybe_allowed_property_values(
+        &self,
+        property: Property,
+        filetype: Filetype,
+    ) -> Result<Option<AllowedValues>> {
         let property = property.to_u32().unwrap();
         let filetype = filetype.to_u32().unwrap();
 
         unsafe {
-            let allowed_values_ptr = std::ptr::null_mut();
+            let mut allowed_values = MaybeUninit::<ffi::LIBMTP_allowed_values_t>::zeroed();
+            let allowed_values_ptr = allowed_values.as_mut_ptr();
 
+            self.clear_errors();
             let res = ffi::LIBMTP_Get_Allowed_Property_Values(
                 self.inner,
                 property,
@@ -442,17 +849,48 @@ impl MtpDevice {
                 allowed_values_ptr,
             );
 
-            if res != 0 || allowed_values_ptr.is_null() {
-                Err(self.latest_error().unwrap_or_default())
-            } else {
-                let allowed_values =
-                    AllowedValues::from_raw(allowed_values_ptr).ok_or(Error::Unknown)?;
-                ffi::LIBMTP_destroy_allowed_values_t(allowed_values_ptr);
-                Ok(allowed_values)
+            if res != 0 {
+                return match self.latest_error() {
+                    Some(err) => Err(err),
+                    None => Ok(None),
+                };
             }
+
+            let allowed_values = AllowedValues::from_raw(allowed_values_ptr);
+            ffi::LIBMTP_destroy_allowed_values_t(allowed_values_ptr);
+            allowed_values.map(Some).ok_or(Error::Unknown)
         }
     }
 
+    /// Lists the properties this device supports for the given file type, together with their
+    /// data type when the device describes the allowed values of the property (string properties
+    /// and properties without a range or enumeration of values have `None`).
+    ///
+    /// Note that `libmtp` doesn't expose whether a property is read-only, and that this call is
+    /// slow since it queries the device once per known property, it's meant for debugging and
+    /// generic tooling.
+    pub fn object_property_list(
+        &self,
+        filetype: Filetype,
+    ) -> Result<Vec<(Property, Option<DataType>)>> {
+        let mut properties = Vec::new();
+        let unknown = Property::Unknown.to_u32().unwrap();
+
+        for property in (0..unknown).filter_map(Property::from_u32) {
+            if !self.is_property_supported(property, filetype.clone())? {
+                continue;
+            }
+
+            let datatype = self
+                .maybe_allowed_property_values(property, filetype.clone())?
+                .map(|values| values.datatype());
+
+            properties.push((property, datatype));
+        }
+
+        Ok(properties)
+    }
+
     /// Build a dummy object, it's useful to work with objects when we only have an
     /// id.
     ///
@@ -474,18 +912,175 @@ impl MtpDevice {
     /// repeatedly, the search is `O(n)`and the call may involve slow USB traffic. Instead use
     /// `Storage::files_and_folders` to cache files.
     pub fn search_file(&self, id: impl AsObjectId) -> Result<File<'_>> {
-        let file = unsafe { ffi::LIBMTP_Get_Filemetadata(self.inner, id.as_id()) };
+        File::from_id(self, id)
+    }
+
+    /// Sends a custom PTP operation with the given operation code and parameters, useful for
+    /// vendor extensions. The operation can't carry a data phase and its response parameters
+    /// aren't returned.
+    ///
+    /// PTP operations take at most 5 parameters, if more are given nothing is sent and
+    /// `Error::TooManyParameters` is returned.
+    pub fn custom_operation(&self, code: u16, params: &[u32]) -> Result<()> {
+        check_custom_operation_params(params)?;
+
+        // The function is variadic, so each number of parameters needs its own call.
+        let n = params.len() as i32;
+        let res = unsafe {
+            match *params {
+                [] => ffi::LIBMTP_Custom_Operation(self.inner, code, n),
+                [p1] => ffi::LIBMTP_Custom_Operation(self.inner, code, n, p1),
+                [p1, p2] => ffi::LIBMTP_Custom_Operation(self.inner, code, n, p1, p2),
+                [p1, p2, p3] => ffi::LIBMTP_Custom_Operation(self.inner, code, n, p1, p2, p3),
+                [p1, p2, p3, p4] => {
+                    ffi::LIBMTP_Custom_Operation(self.inner, code, n, p1, p2, p3, p4)
+                }
+                [p1, p2, p3, p4, p5] => {
+                    ffi::LIBMTP_Custom_Operation(self.inner, code, n, p1, p2, p3, p4, p5)
+                }
+                _ => unreachable!(),
+            }
+        };
 
-        if file.is_null() {
+        if res != 0 {
             Err(self.latest_error().unwrap_or_default())
         } else {
-            Ok(File {
-                inner: file,
-                owner: self,
-            })
+            Ok(())
         }
     }
+}
+
+#[cfg(test)]
+impl MtpDevice {
+    /// Device that is never opened nor released, for tests of methods that don't make requests
+    /// (or fail before making them).
+    pub(crate) fn fake() -> std::mem::ManuallyDrop<MtpDevice> {
+        let raw = RawDevice {
+            inner: unsafe { std::mem::zeroed() },
+        };
+
+        std::mem::ManuallyDrop::new(MtpDevice::from_raw(std::ptr::null_mut(), raw))
+    }
+}
+
+/// Checks that a PTP operation can take the given parameters, see `MtpDevice::custom_operation`.
+fn check_custom_operation_params(params: &[u32]) -> Result<()> {
+    if params.len() > MAX_CUSTOM_OPERATION_PARAMS {
+        Err(Error::TooManyParameters {
+            count: params.len(),
+            max: MAX_CUSTOM_OPERATION_PARAMS,
+        })
+    } else {
+        Ok(())
+    }
+}
+
+/// Detects the raw devices and opens (uncached) the first one whose serial number is `serial`,
+/// the other devices are released. Devices that can't be opened or don't report a serial
+/// number are skipped, returns `Ok(None)` if no device matches.
+pub fn open_by_serial(serial: &str) -> Result<Option<MtpDevice>> {
+    open_matching(|device| device.serial_number().ok().as_deref() == Some(serial))
+}
 
-    // TODO: Custom operation function (c_variadic nightly feature)
-    // pub fn custom_operation(&self, code: u16, params: &[u32]) -> Result<(), ErrorKind>;
+/// Detects the raw devices and opens (uncached) the first one whose friendly name is `name`,
+/// the other devices are released. Devices that can't be opened or don't have a friendly name
+/// are skipped, returns `Ok(None)` if no device matches.
+pub fn open_by_friendly_name(name: &str) -> Result<Option<MtpDevice>> {
+    open_matching(|device| device.get_friendly_name().ok().as_deref() == Some(name))
+}
+
+fn open_matching(matches: impl Fn(&MtpDevice) -> bool) -> Result<Option<MtpDevice>> {
+    let devices = match raw::detect_raw_devices() {
+        Ok(devices) => devices,
+        Err(Error::MtpError {
+            kind: MtpErrorKind::NoDeviceAttached,
+            ..
+        }) => return Ok(None),
+        Err(err) => return Err(err),
+    };
+
+    let device = devices
+        .iter()
+        .filter_map(|raw| raw.try_open_uncached().ok())
+        .find(|device| matches(device));
+
+    Ok(device)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn battery_level_from_levels() {
+        assert!(matches!(
+            BatteryLevel::from_levels(0, 0),
+            BatteryLevel::OnExternalPower
+        ));
+        assert!(matches!(
+            BatteryLevel::from_levels(0, 100),
+            BatteryLevel::Unknown
+        ));
+        assert!(matches!(
+            BatteryLevel::from_levels(42, 100),
+            BatteryLevel::OnBattery(42)
+        ));
+        assert!(matches!(
+            BatteryLevel::from_levels(7, 0),
+            BatteryLevel::OnBattery(7)
+        ));
+    }
+
+    #[test]
+    fn battery_percentage_math() {
+        let battery = Battery::from_levels(0, 0);
+        assert_eq!(battery.state, BatteryState::External);
+        assert_eq!(battery.percentage, None);
+
+        let battery = Battery::from_levels(0, 100);
+        assert_eq!(battery.state, BatteryState::Unknown);
+        assert_eq!(battery.percentage, None);
+
+        let battery = Battery::from_levels(50, 100);
+        assert_eq!(battery.state, BatteryState::Discharging);
+        assert_eq!(battery.percentage, Some(50));
+
+        // Levels above the maximum are clamped.
+        let battery = Battery::from_levels(150, 100);
+        assert_eq!(battery.state, BatteryState::Discharging);
+        assert_eq!(battery.percentage, Some(100));
+
+        // Without a maximum there's nothing to divide by.
+        let battery = Battery::from_levels(3, 0);
+        assert_eq!(battery.state, BatteryState::Discharging);
+        assert_eq!(battery.percentage, None);
+    }
+
+    #[test]
+    fn custom_operation_params_count() {
+        assert!(check_custom_operation_params(&[]).is_ok());
+        assert!(check_custom_operation_params(&[1, 2, 3, 4, 5]).is_ok());
+
+        assert!(matches!(
+            check_custom_operation_params(&[1, 2, 3, 4, 5, 6]),
+            Err(Error::TooManyParameters { count: 6, max: 5 })
+        ));
+    }
+
+    #[test]
+    fn nul_bytes_are_rejected() {
+        let device = MtpDevice::fake();
+
+        assert!(matches!(
+            device.set_friendly_name("bad\0name"),
+            Err(Error::InvalidString { .. })
+        ));
+        assert!(matches!(
+            device.set_sync_partner("bad\0name"),
+            Err(Error::InvalidString { .. })
+        ));
+
+        // The name wasn't changed, so it isn't cached.
+        assert_eq!(device.cached_friendly_name(), None);
+    }
 }
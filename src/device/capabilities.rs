@@ -18,3 +18,147 @@ pub enum DeviceCapability {
     /// This capability tells whether you can copy an object.
     CopyObject,
 }
+
+/// Decoded MTP (and PTP) operation codes, operations without a variant are kept as `Other`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Operation {
+    GetDeviceInfo,
+    OpenSession,
+    CloseSession,
+    GetStorageIds,
+    GetStorageInfo,
+    GetNumObjects,
+    GetObjectHandles,
+    GetObjectInfo,
+    GetObject,
+    GetThumb,
+    DeleteObject,
+    SendObjectInfo,
+    SendObject,
+    FormatStore,
+    ResetDevice,
+    GetDevicePropDesc,
+    GetDevicePropValue,
+    SetDevicePropValue,
+    MoveObject,
+    CopyObject,
+    GetPartialObject,
+    GetObjectPropsSupported,
+    GetObjectPropDesc,
+    GetObjectPropValue,
+    SetObjectPropValue,
+    GetObjectPropList,
+    GetObjectReferences,
+    SetObjectReferences,
+    GetPartialObject64,
+    SendPartialObject,
+    TruncateObject,
+    BeginEditObject,
+    EndEditObject,
+    /// Any other operation code (e.g. vendor extensions).
+    Other(u16),
+}
+
+impl Operation {
+    /// Returns the operation code of this operation.
+    pub fn code(self) -> u16 {
+        match self {
+            Operation::GetDeviceInfo => 0x1001,
+            Operation::OpenSession => 0x1002,
+            Operation::CloseSession => 0x1003,
+            Operation::GetStorageIds => 0x1004,
+            Operation::GetStorageInfo => 0x1005,
+            Operation::GetNumObjects => 0x1006,
+            Operation::GetObjectHandles => 0x1007,
+            Operation::GetObjectInfo => 0x1008,
+            Operation::GetObject => 0x1009,
+            Operation::GetThumb => 0x100a,
+            Operation::DeleteObject => 0x100b,
+            Operation::SendObjectInfo => 0x100c,
+            Operation::SendObject => 0x100d,
+            Operation::FormatStore => 0x100f,
+            Operation::ResetDevice => 0x1010,
+            Operation::GetDevicePropDesc => 0x1014,
+            Operation::GetDevicePropValue => 0x1015,
+            Operation::SetDevicePropValue => 0x1016,
+            Operation::MoveObject => 0x1019,
+            Operation::CopyObject => 0x101a,
+            Operation::GetPartialObject => 0x101b,
+            Operation::GetObjectPropsSupported => 0x9801,
+            Operation::GetObjectPropDesc => 0x9802,
+            Operation::GetObjectPropValue => 0x9803,
+            Operation::SetObjectPropValue => 0x9804,
+            Operation::GetObjectPropList => 0x9805,
+            Operation::GetObjectReferences => 0x9810,
+            Operation::SetObjectReferences => 0x9811,
+            Operation::GetPartialObject64 => 0x95c1,
+            Operation::SendPartialObject => 0x95c2,
+            Operation::TruncateObject => 0x95c3,
+            Operation::BeginEditObject => 0x95c4,
+            Operation::EndEditObject => 0x95c5,
+            Operation::Other(code) => code,
+        }
+    }
+
+    /// Returns the capability that `libmtp` uses to report support for this operation, if any.
+    pub(crate) fn capability(self) -> Option<DeviceCapability> {
+        match self {
+            Operation::GetPartialObject | Operation::GetPartialObject64 => {
+                Some(DeviceCapability::GetPartialObject)
+            }
+            Operation::SendPartialObject => Some(DeviceCapability::SendPartialObject),
+            Operation::TruncateObject | Operation::BeginEditObject | Operation::EndEditObject => {
+                Some(DeviceCapability::EditObjects)
+            }
+            Operation::MoveObject => Some(DeviceCapability::MoveObject),
+            Operation::CopyObject => Some(DeviceCapability::CopyObject),
+            _ => None,
+        }
+    }
+}
+
+impl From<u16> for Operation {
+    fn from(code: u16) -> Self {
+        const KNOWN: [Operation; 33] = [
+            Operation::GetDeviceInfo,
+            Operation::OpenSession,
+            Operation::CloseSession,
+            Operation::GetStorageIds,
+            Operation::GetStorageInfo,
+            Operation::GetNumObjects,
+            Operation::GetObjectHandles,
+            Operation::GetObjectInfo,
+            Operation::GetObject,
+            Operation::GetThumb,
+            Operation::DeleteObject,
+            Operation::SendObjectInfo,
+            Operation::SendObject,
+            Operation::FormatStore,
+            Operation::ResetDevice,
+            Operation::GetDevicePropDesc,
+            Operation::GetDevicePropValue,
+            Operation::SetDevicePropValue,
+            Operation::MoveObject,
+            Operation::CopyObject,
+            Operation::GetPartialObject,
+            Operation::GetObjectPropsSupported,
+            Operation::GetObjectPropDesc,
+            Operation::GetObjectPropValue,
+            Operation::SetObjectPropValue,
+            Operation::GetObjectPropList,
+            Operation::GetObjectReferences,
+            Operation::SetObjectReferences,
+            Operation::GetPartialObject64,
+            Operation::SendPartialObject,
+            Operation::TruncateObject,
+            Operation::BeginEditObject,
+            Operation::EndEditObject,
+        ];
+
+        KNOWN
+            .iter()
+            .copied()
+            .find(|op| op.code() == code)
+            .unwrap_or(Operation::Other(code))
+    }
+}
@@ -0,0 +1,385 @@
+//! Contains the events that an MTP device may report, like objects being added or removed, see
+//! [`MtpDevice::read_event`](../struct.MtpDevice.html#method.read_event).
+
+use libmtp_sys as ffi;
+use std::sync::atomic::{AtomicBool, AtomicI32, Ordering};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+use crate::device::MtpDevice;
+use crate::util::lock;
+
+/// Event reported by an MTP device, together with its parameter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MtpEvent {
+    /// A storage was added (e.g. a card was inserted), contains the storage id.
+    StorageAdded(u32),
+    /// A storage was removed, contains the storage id.
+    StorageRemoved(u32),
+    /// An object was added, contains the object id.
+    ObjectAdded(u32),
+    /// An object was removed, contains the object id.
+    ObjectRemoved(u32),
+    /// A device property changed, contains the property code.
+    DevicePropChanged(u32),
+    /// An event `libmtp` doesn't decode, `code` is the raw `LIBMTP_event_t` (`0` for events
+    /// `libmtp` ignores).
+    Unknown { code: u32, param: u32 },
+}
+
+impl MtpEvent {
+    pub(crate) fn from_raw(event: ffi::LIBMTP_event_t, param: u32) -> Self {
+        match event {
+            ffi::LIBMTP_event_enum_LIBMTP_EVENT_STORE_ADDED => MtpEvent::StorageAdded(param),
+            ffi::LIBMTP_event_enum_LIBMTP_EVENT_STORE_REMOVED => MtpEvent::StorageRemoved(param),
+            ffi::LIBMTP_event_enum_LIBMTP_EVENT_OBJECT_ADDED => MtpEvent::ObjectAdded(param),
+            ffi::LIBMTP_event_enum_LIBMTP_EVENT_OBJECT_REMOVED => MtpEvent::ObjectRemoved(param),
+            ffi::LIBMTP_event_enum_LIBMTP_EVENT_DEVICE_PROPERTY_CHANGED => {
+                MtpEvent::DevicePropChanged(param)
+            }
+
+            code => MtpEvent::Unknown { code, param },
+        }
+    }
+}
+
+/// Handle to a background thread that reads the events of a device, created with
+/// [`MtpDevice::event_thread`](../struct.MtpDevice.html#method.event_thread). The events are
+/// forwarded to a channel, so they can be polled from an event loop with
+/// [`receiver`](#method.receiver).
+///
+/// Dropping this handle stops the thread and joins it. Since `libmtp` can't cancel a read in
+/// flight, the thread waits for it to end first (the device reports an event or is unplugged),
+/// so dropping may block for a long time if the device is idle, use [`detach`](#method.detach)
+/// to stop the thread without waiting. Don't drop it while holding the lock of the device, the
+/// thread needs it to finish the read.
+pub struct EventReceiver {
+    receiver: Receiver<MtpEvent>,
+    stop: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl EventReceiver {
+    /// Returns the channel where the events are forwarded, it's disconnected if reading events
+    /// fails (e.g. the device was unplugged).
+    pub fn receiver(&self) -> &Receiver<MtpEvent> {
+        &self.receiver
+    }
+
+    /// Stops the thread without waiting for it, the thread finishes the read in flight on its own
+    /// and keeps its reference to the device until then, so the device isn't released meanwhile.
+    pub fn detach(mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+        self.thread.take();
+    }
+}
+
+impl Drop for EventReceiver {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::SeqCst);
+
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Longest time the event thread holds the lock of the device while waiting for an event.
+const EVENT_SLICE: Duration = Duration::from_millis(100);
+
+/// Where the event thread reads events from, implemented by the shared device (the tests use a
+/// fake device instead).
+trait EventSource {
+    /// Starts reading the next event, `event_callback` is called with `pending` once the read
+    /// completes, so it must stay alive until then. Returns whether the read started.
+    unsafe fn start_read(&self, pending: *const PendingEvent) -> bool;
+
+    /// Handles the events of the read in flight for at most `EVENT_SLICE`, or until `completed`
+    /// is set.
+    fn handle_events(&self, completed: &AtomicI32);
+}
+
+impl EventSource for Mutex<MtpDevice> {
+    unsafe fn start_read(&self, pending: *const PendingEvent) -> bool {
+        let device = lock(self);
+        let res =
+            ffi::LIBMTP_Read_Event_Async(device.inner, Some(event_callback), pending as *mut _);
+
+        res == 0
+    }
+
+    fn handle_events(&self, completed: &AtomicI32) {
+        let _device = lock(self);
+        let mut timeout = libc::timeval {
+            tv_sec: 0,
+            tv_usec: EVENT_SLICE.as_micros() as _,
+        };
+
+        unsafe {
+            ffi::LIBMTP_Handle_Events_Timeout_Completed(&mut timeout, completed.as_ptr());
+        }
+    }
+}
+
+/// State of an event read in flight. The callback may run in any thread handling libusb events
+/// (e.g. a thread making a request to another device), so it's only accessed through shared
+/// references and the flags are atomic.
+struct PendingEvent {
+    sender: Sender<MtpEvent>,
+    failed: AtomicBool,
+    completed: AtomicI32,
+}
+
+unsafe extern "C" fn event_callback(
+    ret: libc::c_int,
+    event: ffi::LIBMTP_event_t,
+    param: u32,
+    data: *mut libc::c_void,
+) {
+    let pending = &*(data as *const PendingEvent);
+
+    let failed = ret != ffi::LIBMTP_HANDLER_RETURN_OK as libc::c_int
+        || pending
+            .sender
+            .send(MtpEvent::from_raw(event, param))
+            .is_err();
+
+    pending.failed.store(failed, Ordering::Relaxed);
+
+    // Must be the last access, the event thread frees the state once it sees it completed.
+    pending.completed.store(1, Ordering::Release);
+}
+
+fn read_events(source: &impl EventSource, sender: Sender<MtpEvent>, stop: &AtomicBool) {
+    while !stop.load(Ordering::SeqCst) {
+        let pending = Box::into_raw(Box::new(PendingEvent {
+            sender: sender.clone(),
+            failed: AtomicBool::new(false),
+            completed: AtomicI32::new(0),
+        }));
+
+        if !unsafe { source.start_read(pending) } {
+            drop(unsafe { Box::from_raw(pending) });
+            break;
+        }
+
+        // The read can't be cancelled, so it's handled until it completes even if we have to
+        // stop, otherwise the device could be released with the read in flight.
+        let completed = unsafe { &(*pending).completed };
+        while completed.load(Ordering::Acquire) == 0 {
+            source.handle_events(completed);
+
+            // Give other threads waiting for the lock of the device a chance to take it.
+            std::thread::sleep(Duration::from_millis(1));
+        }
+
+        let pending = unsafe { Box::from_raw(pending) };
+        if pending.failed.load(Ordering::Relaxed) {
+            break;
+        }
+    }
+}
+
+fn spawn<S>(source: Arc<S>) -> EventReceiver
+where
+    S: EventSource + Send + Sync + 'static,
+{
+    let (sender, receiver) = mpsc::channel();
+    let stop = Arc::new(AtomicBool::new(false));
+
+    let thread_stop = Arc::clone(&stop);
+    let thread = std::thread::spawn(move || read_events(&*source, sender, &thread_stop));
+
+    EventReceiver {
+        receiver,
+        stop,
+        thread: Some(thread),
+    }
+}
+
+pub(crate) fn event_thread(device: Arc<Mutex<MtpDevice>>) -> EventReceiver {
+    spawn(device)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::VecDeque;
+    use std::thread;
+
+    /// Fake device, its reads complete with the queued events, once there are no events left
+    /// they stay in flight, unless the device is unplugged.
+    #[derive(Default)]
+    struct FakeDevice {
+        state: Mutex<FakeState>,
+    }
+
+    #[derive(Default)]
+    struct FakeState {
+        pending: Option<usize>,
+        events: VecDeque<(ffi::LIBMTP_event_t, u32)>,
+        unplugged: bool,
+    }
+
+    impl FakeDevice {
+        fn with_events(events: &[(ffi::LIBMTP_event_t, u32)], unplugged: bool) -> Arc<Self> {
+            let device = FakeDevice::default();
+            {
+                let mut state = device.state.lock().unwrap();
+                state.events.extend(events);
+                state.unplugged = unplugged;
+            }
+
+            Arc::new(device)
+        }
+
+        fn push_event(&self, event: ffi::LIBMTP_event_t, param: u32) {
+            self.state.lock().unwrap().events.push_back((event, param));
+        }
+
+        fn has_read_in_flight(&self) -> bool {
+            self.state.lock().unwrap().pending.is_some()
+        }
+
+        fn wait_for_read_in_flight(&self) {
+            while !self.has_read_in_flight() {
+                thread::sleep(Duration::from_millis(1));
+            }
+        }
+    }
+
+    impl EventSource for FakeDevice {
+        unsafe fn start_read(&self, pending: *const PendingEvent) -> bool {
+            let mut state = self.state.lock().unwrap();
+            assert!(
+                state.pending.is_none(),
+                "started a read with another in flight"
+            );
+
+            state.pending = Some(pending as usize);
+            true
+        }
+
+        fn handle_events(&self, _completed: &AtomicI32) {
+            let mut state = self.state.lock().unwrap();
+            let pending = state
+                .pending
+                .expect("handled events without a read in flight");
+
+            let ret = match state.events.pop_front() {
+                Some((event, param)) => Some((ffi::LIBMTP_HANDLER_RETURN_OK, event, param)),
+                None if state.unplugged => Some((ffi::LIBMTP_HANDLER_RETURN_ERROR, 0, 0)),
+                None => None,
+            };
+
+            if let Some((ret, event, param)) = ret {
+                state.pending = None;
+                unsafe { event_callback(ret as libc::c_int, event, param, pending as *mut _) };
+            }
+        }
+    }
+
+    impl Drop for FakeDevice {
+        fn drop(&mut self) {
+            assert!(!self.has_read_in_flight(), "released with a read in flight");
+        }
+    }
+
+    #[test]
+    fn decodes_events() {
+        let event = MtpEvent::from_raw(ffi::LIBMTP_event_enum_LIBMTP_EVENT_STORE_ADDED, 1);
+        assert_eq!(event, MtpEvent::StorageAdded(1));
+
+        let event = MtpEvent::from_raw(ffi::LIBMTP_event_enum_LIBMTP_EVENT_STORE_REMOVED, 2);
+        assert_eq!(event, MtpEvent::StorageRemoved(2));
+
+        let event = MtpEvent::from_raw(ffi::LIBMTP_event_enum_LIBMTP_EVENT_OBJECT_ADDED, 3);
+        assert_eq!(event, MtpEvent::ObjectAdded(3));
+
+        let event = MtpEvent::from_raw(ffi::LIBMTP_event_enum_LIBMTP_EVENT_OBJECT_REMOVED, 4);
+        assert_eq!(event, MtpEvent::ObjectRemoved(4));
+
+        let code = ffi::LIBMTP_event_enum_LIBMTP_EVENT_DEVICE_PROPERTY_CHANGED;
+        assert_eq!(MtpEvent::from_raw(code, 5), MtpEvent::DevicePropChanged(5));
+    }
+
+    #[test]
+    fn decodes_unknown_events() {
+        let event = MtpEvent::from_raw(ffi::LIBMTP_event_enum_LIBMTP_EVENT_NONE, 6);
+        assert_eq!(event, MtpEvent::Unknown { code: 0, param: 6 });
+
+        let event = MtpEvent::from_raw(0x4000, 7);
+        assert_eq!(
+            event,
+            MtpEvent::Unknown {
+                code: 0x4000,
+                param: 7
+            }
+        );
+    }
+
+    #[test]
+    fn forwards_events_until_unplugged() {
+        let device = FakeDevice::with_events(
+            &[
+                (ffi::LIBMTP_event_enum_LIBMTP_EVENT_OBJECT_ADDED, 5),
+                (ffi::LIBMTP_event_enum_LIBMTP_EVENT_STORE_REMOVED, 2),
+            ],
+            true,
+        );
+
+        let events = spawn(Arc::clone(&device));
+        let received: Vec<_> = events.receiver().iter().collect();
+
+        assert_eq!(
+            received,
+            [MtpEvent::ObjectAdded(5), MtpEvent::StorageRemoved(2)]
+        );
+
+        drop(events);
+        assert_eq!(Arc::strong_count(&device), 1);
+    }
+
+    #[test]
+    fn starts_and_stops_cleanly() {
+        let device = FakeDevice::with_events(&[], false);
+        let events = spawn(Arc::clone(&device));
+
+        device.push_event(ffi::LIBMTP_event_enum_LIBMTP_EVENT_OBJECT_REMOVED, 7);
+        let event = events.receiver().recv_timeout(Duration::from_secs(5));
+        assert_eq!(event, Ok(MtpEvent::ObjectRemoved(7)));
+
+        device.wait_for_read_in_flight();
+        let dropping = thread::spawn(move || drop(events));
+
+        // The thread can't stop until the read in flight completes.
+        thread::sleep(Duration::from_millis(50));
+        assert!(!dropping.is_finished());
+        assert_eq!(Arc::strong_count(&device), 2);
+
+        device.push_event(ffi::LIBMTP_event_enum_LIBMTP_EVENT_OBJECT_ADDED, 8);
+        dropping.join().unwrap();
+
+        assert!(!device.has_read_in_flight());
+        assert_eq!(Arc::strong_count(&device), 1);
+    }
+
+    #[test]
+    fn detached_thread_keeps_device_until_read_completes() {
+        let device = FakeDevice::with_events(&[], false);
+        let events = spawn(Arc::clone(&device));
+
+        device.wait_for_read_in_flight();
+        events.detach();
+        assert_eq!(Arc::strong_count(&device), 2);
+
+        device.push_event(ffi::LIBMTP_event_enum_LIBMTP_EVENT_OBJECT_ADDED, 1);
+        while Arc::strong_count(&device) > 1 {
+            thread::sleep(Duration::from_millis(1));
+        }
+
+        assert!(!device.has_read_in_flight());
+    }
+}
@@ -8,17 +8,21 @@ use std::mem::MaybeUninit;
 
 use crate::device::MtpDevice;
 use crate::error::{Error, MtpErrorKind};
-use crate::internals::{maybe_init, DeviceEntry};
+use crate::internals::{maybe_init, DeviceEntry, DeviceFlags};
 use crate::Result;
 
 const LIBMTP_UNKNOWN_DEVICE: &str = "UNKNOWN";
 
-/// This struct handles a raw device, which should be opened with `open` or `open_uncached`
+/// This struct handles a raw device, which should be opened with `try_open` or `try_open_uncached`
 /// if you want to manage the proper MTP device.
 pub struct RawDevice {
     pub(crate) inner: ffi::LIBMTP_raw_device_struct,
 }
 
+// The only pointers of a raw device descriptor are the vendor and product names of its device
+// entry, which point to the static device list of libmtp.
+unsafe impl Send for RawDevice {}
+
 impl Debug for RawDevice {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         f.debug_struct("RawDevice")
@@ -30,45 +34,144 @@ impl Debug for RawDevice {
 }
 
 impl RawDevice {
-    /// Open an MTP device from this raw device descriptor, this method
-    /// may cache devices, thus may be slower.
+    /// Open an MTP device from this raw device descriptor, this method may cache devices, thus
+    /// may be slower. See [`try_open`](#method.try_open) to know why opening failed.
+    #[deprecated(note = "use `try_open`, which returns the reason of the failure")]
     pub fn open(&self) -> Option<MtpDevice> {
+        self.try_open().ok()
+    }
+
+    /// Open an MTP device from this raw device descriptor, uncached version. See
+    /// [`try_open_uncached`](#method.try_open_uncached) to know why opening failed.
+    #[deprecated(note = "use `try_open_uncached`, which returns the reason of the failure")]
+    pub fn open_uncached(&self) -> Option<MtpDevice> {
+        self.try_open_uncached().ok()
+    }
+
+    /// Open an MTP device from this raw device descriptor, this method may cache devices, thus
+    /// may be slower.
+    ///
+    /// `libmtp` doesn't report why opening failed, so on Linux the USB device node is checked:
+    /// missing permissions are returned as `Error::Io` (with `io::ErrorKind::PermissionDenied`),
+    /// any other failure (e.g. the device is busy or doesn't really support MTP) is an
+    /// `Error::MtpError` of kind `MtpErrorKind::Connecting`.
+    pub fn try_open(&self) -> Result<MtpDevice> {
         unsafe {
             let ptr = &self.inner as *const _;
             let device = ffi::LIBMTP_Open_Raw_Device(ptr as *mut _);
 
             if device.is_null() {
-                None
+                Err(self.open_error())
             } else {
-                Some(MtpDevice { inner: device })
+                Ok(MtpDevice::from_raw(device, self.duplicate()))
             }
         }
     }
 
-    /// Open an MTP device from this raw device descriptor, uncached version.
-    pub fn open_uncached(&self) -> Option<MtpDevice> {
+    /// Open an MTP device from this raw device descriptor, uncached version. Fails with the same
+    /// errors as [`try_open`](#method.try_open).
+    pub fn try_open_uncached(&self) -> Result<MtpDevice> {
         unsafe {
             let ptr = &self.inner as *const _;
             let device = ffi::LIBMTP_Open_Raw_Device_Uncached(ptr as *mut _);
 
             if device.is_null() {
-                None
+                Err(self.open_error())
             } else {
-                Some(MtpDevice { inner: device })
+                Ok(MtpDevice::from_raw(device, self.duplicate()))
+            }
+        }
+    }
+
+    /// Builds the error of a failed open, see [`try_open`](#method.try_open).
+    fn open_error(&self) -> Error {
+        #[cfg(target_os = "linux")]
+        {
+            let node = format!(
+                "/dev/bus/usb/{:03}/{:03}",
+                self.bus_number(),
+                self.dev_number()
+            );
+
+            let access = std::fs::OpenOptions::new()
+                .read(true)
+                .write(true)
+                .open(node);
+
+            if let Err(err) = access {
+                if err.kind() == std::io::ErrorKind::PermissionDenied {
+                    return err.into();
+                }
             }
         }
+
+        Error::MtpError {
+            kind: MtpErrorKind::Connecting,
+            text: format!(
+                "Couldn't open the device at {}, it may be busy or not support MTP",
+                self.location_string()
+            ),
+        }
+    }
+
+    /// Copies this raw device descriptor, the strings of the device entry are static in
+    /// `libmtp`, so they can be shared.
+    pub(crate) fn duplicate(&self) -> RawDevice {
+        RawDevice {
+            inner: unsafe { std::ptr::read(&self.inner) },
+        }
     }
 
-    /// Returns the bus number of this raw device.
+    /// Returns the bus number of this raw device, it's stable while the device stays connected
+    /// but may change after plugging it again (e.g. in another port).
     pub fn bus_number(&self) -> u32 {
         self.inner.bus_location
     }
 
-    /// Returns the device number of this raw device.
+    /// Returns the device number of this raw device, it's assigned by the host on every
+    /// connection so it usually changes after plugging the device again.
     pub fn dev_number(&self) -> u8 {
         self.inner.devnum
     }
 
+    /// Returns the USB location of this raw device formatted for display (e.g. `"bus 1, dev 5"`),
+    /// together with the device entry this distinguishes two identical devices connected at the
+    /// same time. See [`persistent_id`](#method.persistent_id) for an identifier to store.
+    pub fn location_string(&self) -> String {
+        format!("bus {}, dev {}", self.bus_number(), self.dev_number())
+    }
+
+    /// Returns an string that identifies this raw device, made of its vendor id, product id, bus
+    /// number and device number (e.g. `"04e8:6860@1-5"`). You may store it to find the same device
+    /// later with [`find_device_by_persistent_id`](fn.find_device_by_persistent_id.html).
+    ///
+    /// Note that the bus and device numbers may change when the device is plugged again.
+    pub fn persistent_id(&self) -> String {
+        let entry = &self.inner.device_entry;
+
+        format!(
+            "{:04x}:{:04x}@{}-{}",
+            entry.vendor_id,
+            entry.product_id,
+            self.bus_number(),
+            self.dev_number()
+        )
+    }
+
+    /// Heuristic to know beforehand if opening this raw device is expected to work, based on its
+    /// device flags, useful if you want to warn about a device before trying to open it.
+    ///
+    /// The `BROKEN_*` flags (and the other quirks) don't disqualify a device, `libmtp` works
+    /// around them once the device is open, they only make some requests slower or unsupported.
+    /// The only case that returns `false` is `DeviceFlags::UNLOAD_DRIVER` on platforms other
+    /// than Linux: `libmtp` has to detach the kernel driver (e.g. mass storage) that claims the
+    /// interface, which `libusb` only supports on Linux, so elsewhere the interface stays busy.
+    ///
+    /// Note that returning `true` doesn't guarantee that `open` will succeed.
+    pub fn likely_openable(&self) -> bool {
+        openable_with(self.device_entry().flags(), cfg!(target_os = "linux"))
+    }
+
     /// Returns the device entry of this raw device.
     pub fn device_entry(&self) -> DeviceEntry {
         let vendor = unsafe {
@@ -96,6 +199,12 @@ impl RawDevice {
     }
 }
 
+/// Whether `libmtp` can handle a device with the given flags, on Linux or elsewhere, see
+/// `RawDevice::likely_openable`.
+fn openable_with(flags: DeviceFlags, linux: bool) -> bool {
+    linux || !flags.contains(DeviceFlags::UNLOAD_DRIVER)
+}
+
 /// Detect the raw device descriptors, you will use this function whenever you want
 /// to find which devices are connected, then you may open one or all of these devices,
 /// to properly manage the device properties, its storage, files, etc.
@@ -109,7 +218,7 @@ impl RawDevice {
 /// // Try to open the first device
 /// let mtp_device = raw_devices
 ///                     .get(0)
-///                     .map(|r| r.open_uncached())
+///                     .map(|r| r.try_open_uncached())
 ///                     .transpose()
 ///                     .expect("Couldn't open raw device");
 /// ```
@@ -130,10 +239,12 @@ pub fn detect_raw_devices() -> Result<Vec<RawDevice>> {
         } else {
             let mut devices_vec = Vec::with_capacity(len as usize);
             for i in 0..(len as isize) {
-                let mut new = MaybeUninit::zeroed().assume_init();
+                let mut new = MaybeUninit::<ffi::LIBMTP_raw_device_struct>::uninit();
 
-                std::ptr::copy_nonoverlapping(devices.offset(i), &mut new, 1);
-                devices_vec.push(RawDevice { inner: new });
+                std::ptr::copy_nonoverlapping(devices.offset(i), new.as_mut_ptr(), 1);
+                devices_vec.push(RawDevice {
+                    inner: new.assume_init(),
+                });
             }
 
             libc::free(devices as *mut _);
@@ -142,9 +253,96 @@ pub fn detect_raw_devices() -> Result<Vec<RawDevice>> {
     }
 }
 
+/// Parses a persistent id into `(vendor_id, product_id, bus_number, dev_number)`.
+fn parse_persistent_id(id: &str) -> Option<(u16, u16, u32, u8)> {
+    let (ids, location) = id.split_once('@')?;
+    let (vendor_id, product_id) = ids.split_once(':')?;
+    let (bus_number, dev_number) = location.split_once('-')?;
+
+    Some((
+        u16::from_str_radix(vendor_id, 16).ok()?,
+        u16::from_str_radix(product_id, 16).ok()?,
+        bus_number.parse().ok()?,
+        dev_number.parse().ok()?,
+    ))
+}
+
+/// Detects the raw devices and looks for the one identified by `id`, as returned by
+/// [`RawDevice::persistent_id`](struct.RawDevice.html#method.persistent_id).
+///
+/// Given that the bus and device numbers may change when a device is plugged again, if there
+/// isn't an exact match this falls back to the first device with the same vendor and product
+/// ids. Returns `Ok(None)` if `id` is malformed or there's no matching device connected.
+pub fn find_device_by_persistent_id(id: &str) -> Result<Option<RawDevice>> {
+    let (vendor_id, product_id, bus_number, dev_number) = match parse_persistent_id(id) {
+        Some(parsed) => parsed,
+        None => return Ok(None),
+    };
+
+    let devices = match detect_raw_devices() {
+        Ok(devices) => devices,
+        Err(Error::MtpError {
+            kind: MtpErrorKind::NoDeviceAttached,
+            ..
+        }) => return Ok(None),
+        Err(err) => return Err(err),
+    };
+
+    let mut candidates: Vec<_> = devices
+        .into_iter()
+        .filter(|raw| {
+            let entry = &raw.inner.device_entry;
+            entry.vendor_id == vendor_id && entry.product_id == product_id
+        })
+        .collect();
+
+    let exact = candidates
+        .iter()
+        .position(|raw| raw.bus_number() == bus_number && raw.dev_number() == dev_number);
+
+    match exact {
+        Some(index) => Ok(Some(candidates.swap_remove(index))),
+        None if candidates.is_empty() => Ok(None),
+        None => Ok(Some(candidates.swap_remove(0))),
+    }
+}
+
+/// Async version of [`detect_raw_devices`](fn.detect_raw_devices.html), the detection runs on a
+/// background thread so it doesn't block the executor (detecting may take seconds if a device
+/// is slow to respond). Only available with the `async` feature.
+#[cfg(feature = "async")]
+pub async fn detect_raw_devices_async() -> Result<Vec<RawDevice>> {
+    crate::util::spawn_blocking(detect_raw_devices).await
+}
+
 /// Check if a specific device, given its bus and device number, has an
 /// MTP type device descriptor.
 pub fn check_specific_device(bus_number: u32, dev_number: u32) -> bool {
     let res = unsafe { ffi::LIBMTP_Check_Specific_Device(bus_number as i32, dev_number as i32) };
     res == 1
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quirks_are_worked_around() {
+        let flags = DeviceFlags::BROKEN_MTPGETOBJPROPLIST_ALL
+            | DeviceFlags::BROKEN_SEND_OBJECT_PROPLIST
+            | DeviceFlags::BROKEN_BATTERY_LEVEL
+            | DeviceFlags::SWITCH_MODE_BLACKBERRY;
+
+        assert!(openable_with(DeviceFlags::NONE, false));
+        assert!(openable_with(flags, false));
+        assert!(openable_with(flags, true));
+    }
+
+    #[test]
+    fn unloading_drivers_needs_linux() {
+        let flags = DeviceFlags::UNLOAD_DRIVER | DeviceFlags::BROKEN_MTPGETOBJPROPLIST;
+
+        assert!(openable_with(flags, true));
+        assert!(!openable_with(flags, false));
+    }
+}
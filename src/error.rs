@@ -1,7 +1,12 @@
 //! This module contains information about possible errors, such as internal and `libmtp` errors.
 
+use crate::device::capabilities::DeviceCapability;
+use crate::object::properties::Property;
 use libmtp_sys as ffi;
+use std::ffi::NulError;
+use std::io;
 use std::string::FromUtf8Error;
+use std::sync::Arc;
 use thiserror::Error as ErrorTrait;
 
 /// Enumeration of possible `libmtp` errors, check
@@ -55,6 +60,86 @@ pub enum Error {
     /// Internal error when converting strings with invalid UTF-8 encoding.
     #[error("Utf8 error ({source})")]
     Utf8Error { source: FromUtf8Error },
+
+    /// A string given to `libmtp` contains an interior nul byte, so it can't be converted into a
+    /// C string.
+    #[error("Invalid string ({source})")]
+    InvalidString { source: NulError },
+
+    /// Error of the local file system (e.g. a local file that doesn't exist), the source is kept
+    /// behind an `Arc` so this error stays cloneable.
+    #[error("IO error ({source})")]
+    Io { source: Arc<io::Error> },
+
+    /// There isn't an object with the given id in the device (or it couldn't be retrieved
+    /// without `libmtp` reporting an error).
+    #[error("Object not found (id: {id})")]
+    ObjectNotFound { id: u32 },
+
+    /// The given order of tracks isn't a permutation of the current tracks of a playlist.
+    #[error("Invalid order, must be a permutation of the current tracks")]
+    InvalidOrder,
+
+    /// Tried to nest folders in a storage with a flat file system (`FilesystemType::GenericFlat`).
+    #[error("Storage has a flat file system, can't nest folders (storage id: {storage_id})")]
+    FlatFilesystem { storage_id: u32 },
+
+    /// Tried to send an object to a storage that doesn't accept writes (its access capability
+    /// isn't `AccessCapability::ReadWrite`).
+    #[error("Storage is read-only (storage id: {storage_id})")]
+    ReadOnlyStorage { storage_id: u32 },
+
+    /// The given parent folder doesn't exist in the storage.
+    #[error("Invalid parent, folder doesn't exist (id: {id})")]
+    InvalidParent { id: u32 },
+
+    /// The metadata given to send a file is inconsistent (e.g. empty file name).
+    #[error("Invalid file metadata: {reason}")]
+    InvalidMetadata { reason: &'static str },
+
+    /// The object is larger than the capacity of the storage where it was going to be sent.
+    #[error("Object too large ({size} bytes, max {max} bytes)")]
+    ObjectTooLarge { size: u64, max: u64 },
+
+    /// The amount of bytes sent doesn't match the declared size of the object.
+    #[error("Size mismatch (declared {expected} bytes, sent {written} bytes)")]
+    SizeMismatch { expected: u64, written: u64 },
+
+    /// The given number of stars is out of range (`0..=5`).
+    #[error("Invalid rating, {stars} stars is out of range (0..=5)")]
+    InvalidRating { stars: u8 },
+
+    /// The folder can't be deleted since it isn't empty.
+    #[error("Folder {id} isn't empty")]
+    FolderNotEmpty { id: u32 },
+
+    /// The device doesn't support the capability needed by the operation.
+    #[error("Unsupported capability ({capability:?})")]
+    UnsupportedCapability { capability: DeviceCapability },
+
+    /// More parameters than the ones an operation can take were given.
+    #[error("Too many parameters ({count}, max {max})")]
+    TooManyParameters { count: usize, max: usize },
+
+    /// The data type of the values of the property isn't known (or they are arrays), so it can't
+    /// be read as a `PropertyValue`.
+    #[error("Unknown data type for property {property:?}")]
+    UnknownPropertyType { property: Property },
+
+    /// The temporary folder created by `Storage::probe_writable` couldn't be deleted, so it's
+    /// left behind in the storage.
+    #[error("Probe folder {id} couldn't be deleted ({source})")]
+    ProbeNotDeleted { id: u32, source: Box<Error> },
+
+    /// The name of a file in the device can't be used as a local file name (e.g. it's empty or
+    /// `..`).
+    #[error("Invalid local file name {name:?}")]
+    InvalidFileName { name: String },
+
+    /// Many files would be retrieved to the same local file, since their names are the same
+    /// (or become the same once sanitized).
+    #[error("Duplicated local file name {name:?}")]
+    DuplicateFileName { name: String },
 }
 
 impl Default for Error {
@@ -64,22 +149,25 @@ impl Default for Error {
 }
 
 impl Error {
-    pub(crate) unsafe fn from_latest_error(mut list: *const ffi::LIBMTP_error_t) -> Option<Self> {
-        if list.is_null() {
-            None
-        } else {
-            while !(*list).next.is_null() {
-                list = (*list).next;
-            }
+    /// Collects every error of an error stack, from the oldest to the latest, nodes without a
+    /// known error number are skipped.
+    pub(crate) unsafe fn from_error_stack(mut list: *const ffi::LIBMTP_error_t) -> Vec<Self> {
+        let mut errors = Vec::new();
 
+        while !list.is_null() {
             let error_t = &*list;
 
-            let kind = MtpErrorKind::from_error_number(error_t.errornumber)?;
-            let u8vec = cstr_to_u8vec!(error_t.error_text);
-            let text = String::from_utf8_lossy(&u8vec).into_owned();
+            if let Some(kind) = MtpErrorKind::from_error_number(error_t.errornumber) {
+                let u8vec = cstr_to_u8vec!(error_t.error_text);
+                let text = String::from_utf8_lossy(&u8vec).into_owned();
 
-            Some(Error::MtpError { kind, text })
+                errors.push(Error::MtpError { kind, text });
+            }
+
+            list = error_t.next;
         }
+
+        errors
     }
 }
 
@@ -88,3 +176,17 @@ impl From<FromUtf8Error> for Error {
         Error::Utf8Error { source }
     }
 }
+
+impl From<NulError> for Error {
+    fn from(source: NulError) -> Self {
+        Error::InvalidString { source }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(source: io::Error) -> Self {
+        Error::Io {
+            source: Arc::new(source),
+        }
+    }
+}
@@ -4,18 +4,30 @@
 use bitflags::bitflags;
 use libmtp_sys as ffi;
 use std::ffi::CStr;
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::Once;
 
 use crate::error::Error;
 use crate::Result;
 
+/// Initializes `libmtp` explicitly, subsequent calls are no-ops. Every function that needs
+/// `libmtp` to be initialized (e.g. `detect_raw_devices` or `set_debug`) already calls this, so
+/// you only need it if you want to control *when* the initialization happens, e.g. at startup.
+///
+/// ## Example
+/// ```no_run
+/// use libmtp_rs::internals::{self, DebugLevel};
+///
+/// internals::init();
+/// internals::set_debug(DebugLevel::PTP | DebugLevel::USB);
+/// ```
+pub fn init() {
+    static INIT: Once = Once::new();
+    INIT.call_once(|| unsafe { ffi::LIBMTP_Init() });
+}
+
 pub(crate) fn maybe_init() {
-    static mut ALREADY_INIT: bool = false;
-    unsafe {
-        if !ALREADY_INIT {
-            ffi::LIBMTP_Init();
-            ALREADY_INIT = true;
-        }
-    }
+    init();
 }
 
 bitflags! {
@@ -55,6 +67,165 @@ pub fn set_debug(level: DebugLevel) {
     unsafe {
         ffi::LIBMTP_Set_Debug(level.bits());
     }
+
+    DEBUG_LEVEL.store(level.bits(), Ordering::SeqCst);
+}
+
+/// Latest debug level set with `set_debug`, `libmtp` doesn't expose a getter.
+static DEBUG_LEVEL: AtomicI32 = AtomicI32::new(ffi::LIBMTP_DEBUG_NONE as i32);
+
+/// Returns the current debug level of libmtp (C library), that is the latest level set with
+/// [`set_debug`](fn.set_debug.html), or `DebugLevel::NONE` if it was never called.
+///
+/// ## Example
+/// ```
+/// use libmtp_rs::internals::{debug_level, set_debug, DebugLevel};
+///
+/// set_debug(DebugLevel::PTP | DebugLevel::DATA);
+/// assert!(debug_level().contains(DebugLevel::PTP));
+/// ```
+pub fn debug_level() -> DebugLevel {
+    DebugLevel::from_bits_truncate(DEBUG_LEVEL.load(Ordering::SeqCst))
+}
+
+bitflags! {
+    /// Bitflags describing the quirks `libmtp` knows about a device, these are the
+    /// `DEVICE_FLAG_*` values from
+    /// [`device-flags.h`](https://github.com/libmtp/libmtp/blob/master/src/device-flags.h).
+    pub struct DeviceFlags: u32 {
+        const NONE = 0x0000_0000;
+        const BROKEN_MTPGETOBJPROPLIST_ALL = 0x0000_0001;
+        const UNLOAD_DRIVER = 0x0000_0002;
+        const BROKEN_MTPGETOBJPROPLIST = 0x0000_0004;
+        const NO_ZERO_READS = 0x0000_0008;
+        const IRIVER_OGG_ALZHEIMER = 0x0000_0010;
+        const ONLY_7BIT_FILENAMES = 0x0000_0020;
+        const NO_RELEASE_INTERFACE = 0x0000_0040;
+        const IGNORE_HEADER_ERRORS = 0x0000_0080;
+        const BROKEN_SET_OBJECT_PROPLIST = 0x0000_0100;
+        const OGG_IS_UNKNOWN = 0x0000_0200;
+        const BROKEN_SET_SAMPLE_DIMENSIONS = 0x0000_0400;
+        const ALWAYS_PROBE_DESCRIPTOR = 0x0000_0800;
+        const PLAYLIST_SPL_V1 = 0x0000_1000;
+        const PLAYLIST_SPL_V2 = 0x0000_2000;
+        const CANNOT_HANDLE_DATEMODIFIED = 0x0000_4000;
+        const BROKEN_SEND_OBJECT_PROPLIST = 0x0000_8000;
+        const BROKEN_BATTERY_LEVEL = 0x0001_0000;
+        const DELETE_SENDS_EVENT = 0x0002_0000;
+        const CAPTURE = 0x0004_0000;
+        const CAPTURE_PREVIEW = 0x0008_0000;
+        const NIKON_BROKEN_CAPTURE = 0x0010_0000;
+        const NIKON_1 = 0x0020_0000;
+        const NO_CAPTURE_COMPLETE = 0x0040_0000;
+        const OLYMPUS_XML_WRAPPED = 0x0080_0000;
+        const FLAC_IS_UNKNOWN = 0x0100_0000;
+        const UNIQUE_FILENAMES = 0x0200_0000;
+        const SWITCH_MODE_BLACKBERRY = 0x0400_0000;
+        const LONG_TIMEOUT = 0x0800_0000;
+        const FORCE_RESET_ON_CLOSE = 0x1000_0000;
+        const DONT_CLOSE_SESSION = 0x2000_0000;
+        const PROPLIST_OVERRIDES_OI = 0x4000_0000;
+        const SAMSUNG_OFFSET_BUG = 0x8000_0000;
+    }
+}
+
+/// Restores the standard output and error when dropped, so they're restored even if the
+/// closure given to `capture_debug` panics.
+#[cfg(unix)]
+struct RedirectGuard {
+    saved_stdout: libc::c_int,
+    saved_stderr: libc::c_int,
+}
+
+#[cfg(unix)]
+impl Drop for RedirectGuard {
+    fn drop(&mut self) {
+        use std::io::Write;
+
+        let _ = std::io::stdout().flush();
+
+        unsafe {
+            libc::fflush(std::ptr::null_mut());
+            libc::dup2(self.saved_stdout, libc::STDOUT_FILENO);
+            libc::dup2(self.saved_stderr, libc::STDERR_FILENO);
+            libc::close(self.saved_stdout);
+            libc::close(self.saved_stderr);
+        }
+    }
+}
+
+/// Runs `f` capturing everything `libmtp` (C library) prints while it runs, and returns the
+/// result of `f` together with the captured text. This is useful to correlate an error returned
+/// by this crate with the debug trace of `libmtp`, see [`set_debug`](fn.set_debug.html).
+///
+/// Note that `libmtp` writes its debug trace to the standard output and its errors to the
+/// standard error, both are captured into the same buffer. Since this redirects the file
+/// descriptors of the whole process, output from other threads will be captured too.
+///
+/// ## Example
+/// ```no_run
+/// use libmtp_rs::internals::{capture_debug, set_debug, DebugLevel};
+/// use libmtp_rs::device::raw::detect_raw_devices;
+///
+/// set_debug(DebugLevel::ALL);
+/// let (devices, trace) = capture_debug(detect_raw_devices);
+/// ```
+#[cfg(unix)]
+pub fn capture_debug<F, R>(f: F) -> (R, String)
+where
+    F: FnOnce() -> R,
+{
+    use std::io::{Read, Write};
+    use std::mem::ManuallyDrop;
+    use std::os::unix::io::FromRawFd;
+
+    unsafe {
+        let capture = libc::tmpfile();
+        if capture.is_null() {
+            return (f(), String::new());
+        }
+
+        let capture_fd = libc::fileno(capture);
+        let saved_stdout = libc::dup(libc::STDOUT_FILENO);
+        let saved_stderr = libc::dup(libc::STDERR_FILENO);
+
+        // Without a copy of both descriptors they couldn't be restored afterwards, so don't
+        // redirect anything.
+        if capture_fd == -1 || saved_stdout == -1 || saved_stderr == -1 {
+            for fd in [saved_stdout, saved_stderr] {
+                if fd != -1 {
+                    libc::close(fd);
+                }
+            }
+
+            libc::fclose(capture);
+            return (f(), String::new());
+        }
+
+        let _ = std::io::stdout().flush();
+        libc::fflush(std::ptr::null_mut());
+
+        let guard = RedirectGuard {
+            saved_stdout,
+            saved_stderr,
+        };
+
+        libc::dup2(capture_fd, libc::STDOUT_FILENO);
+        libc::dup2(capture_fd, libc::STDERR_FILENO);
+
+        let result = f();
+        drop(guard);
+
+        // The descriptor is still owned by `capture`, it's closed by `fclose` below.
+        let mut output = Vec::new();
+        let mut file = ManuallyDrop::new(std::fs::File::from_raw_fd(capture_fd));
+        if libc::lseek(capture_fd, 0, libc::SEEK_SET) == 0 {
+            let _ = file.read_to_end(&mut output);
+        }
+        libc::fclose(capture);
+
+        (result, String::from_utf8_lossy(&output).into_owned())
+    }
 }
 
 /// Contains information about the devices `libmtp` supports. More information
@@ -68,6 +239,13 @@ pub struct DeviceEntry {
     pub device_flags: u32,
 }
 
+impl DeviceEntry {
+    /// Decodes the raw `device_flags` of this entry, unknown bits are dropped.
+    pub fn flags(&self) -> DeviceFlags {
+        DeviceFlags::from_bits_truncate(self.device_flags)
+    }
+}
+
 /// Retrieves the devices `libmtp` claims to support as stated in
 /// [`music-players.h`](https://github.com/libmtp/libmtp/blob/master/src/music-players.h).
 pub fn get_supported_devices() -> Result<Vec<DeviceEntry>> {
@@ -101,3 +279,25 @@ pub fn get_supported_devices() -> Result<Vec<DeviceEntry>> {
         Ok(devices)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    #[test]
+    fn captures_output_and_restores_it() {
+        let (result, output) = capture_debug(|| unsafe {
+            let text = b"captured\n";
+            libc::write(libc::STDERR_FILENO, text.as_ptr() as *const _, text.len());
+            42
+        });
+
+        assert_eq!(result, 42);
+        assert!(output.contains("captured"));
+
+        for fd in [libc::STDOUT_FILENO, libc::STDERR_FILENO] {
+            assert_ne!(unsafe { libc::fcntl(fd, libc::F_GETFD) }, -1);
+        }
+    }
+}
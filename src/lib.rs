@@ -34,6 +34,8 @@ pub mod device;
 pub mod object;
 pub mod storage;
 
+pub use internals::init;
+
 /// Re-export for support convenience.
 pub use chrono;
 
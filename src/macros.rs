@@ -55,6 +55,8 @@ macro_rules! path_to_cvec {
     }};
 }
 
+/// Fills a `LIBMTP_file_t` with the given metadata, evaluates to `crate::Result<()>`. On failure
+/// (i.e. the file name has a nul byte) the `LIBMTP_file_t` is destroyed.
 macro_rules! fill_file_t {
     ($filemetadata:expr, $parent:expr, $storage:expr, $file:ident) => {{
         use num_traits::ToPrimitive;
@@ -63,16 +65,25 @@ macro_rules! fill_file_t {
         let file_t = $file;
         let metadata = $filemetadata;
 
-        (*file_t).parent_id = $parent;
-        (*file_t).storage_id = $storage;
-        (*file_t).filesize = metadata.file_size;
-        (*file_t).filetype = metadata
-            .file_type
-            .to_u32()
-            .expect("Unexpected variant in Filetype");
-        (*file_t).modificationdate = metadata.modification_date.timestamp() as libc::time_t;
+        match CString::new(metadata.file_name) {
+            Ok(filename) => {
+                (*file_t).parent_id = $parent;
+                (*file_t).storage_id = $storage;
+                (*file_t).filesize = metadata.file_size;
+                (*file_t).filetype = metadata
+                    .file_type
+                    .to_u32()
+                    .expect("Unexpected variant in Filetype");
+                (*file_t).modificationdate = metadata.modification_date.timestamp() as libc::time_t;
+                (*file_t).filename = libc::strdup(filename.as_c_str().as_ptr());
 
-        let filename = CString::new(metadata.file_name).unwrap();
-        (*file_t).filename = libc::strdup(filename.as_c_str().as_ptr());
+                Ok(())
+            }
+
+            Err(source) => {
+                ffi::LIBMTP_destroy_file_t(file_t);
+                Err(crate::error::Error::InvalidString { source })
+            }
+        }
     }};
 }
@@ -6,16 +6,22 @@
 
 pub mod filetypes;
 pub mod properties;
+pub mod samples;
 
 use std::ffi::CString;
 
-use crate::device::MtpDevice;
-use crate::storage::Parent;
+use crate::device::capabilities::DeviceCapability;
+use crate::device::{DeviceId, MtpDevice};
+use crate::error::{Error, MtpErrorKind};
+use crate::storage::{files, Parent};
+use crate::util::{HandlerReturn, TransferOutcome};
+use crate::values::{DataType, PropertyValue};
 use crate::Result;
 
 use libmtp_sys as ffi;
 use num_traits::ToPrimitive;
 use properties::Property;
+use samples::SampleData;
 
 /// Trait to allow the usage of certain structures or plain `u32` in places where an object id is
 /// required. By default every `Object` implementor automagically implements this trait.
@@ -77,31 +83,42 @@ pub trait Object {
     /// Must return a valid reference of an `MtpDevice`, where this object resides in.
     fn device(&self) -> &MtpDevice;
 
-    /// Retrieves a string from an object attribute.
-    fn get_string(&self, property: Property) -> Result<String> {
-        let property = property.to_u32().unwrap();
-        let id = self.id();
-        let device = self.device();
+    /// Returns the id of the device where this object resides in, objects from different devices
+    /// may have the same object id, so this can be used to tell them apart.
+    fn device_id(&self) -> DeviceId {
+        self.device().device_id()
+    }
 
-        let string = unsafe { ffi::LIBMTP_Get_String_From_Object(device.inner, id, property) };
+    /// Retrieves a string from an object attribute, fails if the attribute is unset, use
+    /// [`get_string_opt`](#method.get_string_opt) if an unset attribute is a valid state for you.
+    fn get_string(&self, property: Property) -> Result<String> {
+        self.get_string_opt(property)?.ok_or(Error::Unknown)
+    }
 
-        if string.is_null() {
-            Err(device.latest_error().unwrap_or_default())
-        } else {
-            unsafe {
-                let u8vec = cstr_to_u8vec!(string);
-                libc::free(string as *mut _);
-                Ok(String::from_utf8(u8vec)?)
-            }
+    /// Retrieves a string from an object attribute, returns `Ok(None)` if the attribute is unset
+    /// (e.g. a track without artist), `Err` is reserved for actual `libmtp` errors.
+    fn get_string_opt(&self, property: Property) -> Result<Option<String>> {
+        match get_raw_string(self, property)? {
+            Some(u8vec) => Ok(Some(String::from_utf8(u8vec)?)),
+            None => Ok(None),
         }
     }
 
-    /// Sets an object attribute from a string.
+    /// Retrieves a string from an object attribute like [`get_string`](#method.get_string), but
+    /// invalid UTF-8 sequences (some devices store metadata with other encodings) are replaced
+    /// with `U+FFFD REPLACEMENT CHARACTER` instead of failing.
+    fn get_string_lossy(&self, property: Property) -> Result<String> {
+        let u8vec = get_raw_string(self, property)?.ok_or(Error::Unknown)?;
+        Ok(String::from_utf8_lossy(&u8vec).into_owned())
+    }
+
+    /// Sets an object attribute from a string, the string is given to `libmtp` as UTF-8 (which
+    /// takes care of the encoding used by MTP).
     fn set_string(&self, property: Property, string: &str) -> Result<()> {
         let property = property.to_u32().unwrap();
         let id = self.id();
         let device = self.device();
-        let string = CString::new(string).expect("Nul byte");
+        let string = CString::new(string)?;
 
         let res =
             unsafe { ffi::LIBMTP_Set_Object_String(device.inner, id, property, string.as_ptr()) };
@@ -119,6 +136,7 @@ pub trait Object {
         let id = self.id();
         let device = self.device();
 
+        device.clear_errors();
         let val = unsafe { ffi::LIBMTP_Get_u64_From_Object(device.inner, id, property, 0) };
 
         if let Some(err) = device.latest_error() {
@@ -134,6 +152,7 @@ pub trait Object {
         let id = self.id();
         let device = self.device();
 
+        device.clear_errors();
         let val = unsafe { ffi::LIBMTP_Get_u32_From_Object(device.inner, id, property, 0) };
 
         if let Some(err) = device.latest_error() {
@@ -164,6 +183,7 @@ pub trait Object {
         let id = self.id();
         let device = self.device();
 
+        device.clear_errors();
         let val = unsafe { ffi::LIBMTP_Get_u16_From_Object(device.inner, id, property, 0) };
 
         if let Some(err) = device.latest_error() {
@@ -194,6 +214,7 @@ pub trait Object {
         let id = self.id();
         let device = self.device();
 
+        device.clear_errors();
         let val = unsafe { ffi::LIBMTP_Get_u8_From_Object(device.inner, id, property, 0) };
 
         if let Some(err) = device.latest_error() {
@@ -217,6 +238,55 @@ pub trait Object {
             Ok(())
         }
     }
+    /// Retrieves an object attribute with the getter that matches the data type of the
+    /// property (see `Property::datatype`), so integers of the wrong width aren't read by
+    /// mistake. Fails with `Error::UnknownPropertyType` if the property isn't an integer nor a
+    /// string (e.g. array properties).
+    fn get_property_value(&self, property: Property) -> Result<PropertyValue> {
+        let value = match property.datatype() {
+            Some(DataType::I8) => PropertyValue::I8(self.get_u8(property)? as i8),
+            Some(DataType::U8) => PropertyValue::U8(self.get_u8(property)?),
+            Some(DataType::I16) => PropertyValue::I16(self.get_u16(property)? as i16),
+            Some(DataType::U16) => PropertyValue::U16(self.get_u16(property)?),
+            Some(DataType::I32) => PropertyValue::I32(self.get_u32(property)? as i32),
+            Some(DataType::U32) => PropertyValue::U32(self.get_u32(property)?),
+            Some(DataType::I64) => PropertyValue::I64(self.get_u64(property)? as i64),
+            Some(DataType::U64) => PropertyValue::U64(self.get_u64(property)?),
+            None if property.is_string() => PropertyValue::String(self.get_string(property)?),
+            None => return Err(Error::UnknownPropertyType { property }),
+        };
+
+        Ok(value)
+    }
+
+    /// Retrieves the rating of this object in a `0..=100` scale, returns `Ok(None)` if the object
+    /// isn't rated (a rating of `0`).
+    fn rating(&self) -> Result<Option<u8>> {
+        let rating = self.get_u16(Property::Rating)?;
+
+        if rating == 0 {
+            Ok(None)
+        } else {
+            Ok(Some(rating.min(100) as u8))
+        }
+    }
+
+    /// Retrieves the rating of this object as stars (`0..=5`, rounded), returns `None` if the
+    /// object isn't rated or the rating couldn't be retrieved.
+    fn rating_stars(&self) -> Option<u8> {
+        let rating = self.rating().ok()??;
+        Some(((rating as u16 + 10) / 20) as u8)
+    }
+
+    /// Sets the rating of this object from stars (`0..=5`), e.g. 3 stars is a rating of `60`.
+    /// Returns `Error::InvalidRating` if `stars` is greater than 5.
+    fn set_rating_stars(&self, stars: u8) -> Result<()> {
+        if stars > 5 {
+            return Err(Error::InvalidRating { stars });
+        }
+
+        self.set_u16(Property::Rating, stars as u16 * 20)
+    }
 
     /// Deletes a *single* file, track, playlist, folder or any other object off the MTP device.
     /// Note that deleting folders may no be remove its contents, in turn this is the expected
@@ -224,7 +294,9 @@ pub trait Object {
     ///
     /// If you want to delete a folder first recursively delete all files and folders contained in
     /// this folder, then the folder itself. Finally, if the operation is sucessful you should
-    /// discard the object given that now it holds an **invalid id**.
+    /// discard the object given that now it holds an **invalid id**, nothing prevents using it
+    /// afterwards (`File::delete_consume` and `Folder::delete_consume` consume the handle
+    /// instead).
     fn delete(&self) -> Result<()> {
         let id = self.id();
         let device = self.device();
@@ -258,6 +330,35 @@ pub trait Object {
         }
     }
 
+    /// Same as [`move_to`](#method.move_to), but once the object is moved its location is read
+    /// back from the device and returned as `(storage_id, parent)`. Some devices may put the
+    /// object in a different parent than the requested one due to their own restrictions.
+    fn move_to_returning(&self, storage_id: u32, parent: Parent) -> Result<(u32, Parent)> {
+        self.move_to(storage_id, parent)?;
+
+        let storage_id = self.get_u32(Property::StorageId)?;
+        let parent = match self.get_u32(Property::ParentObject)? {
+            0 | ffi::LIBMTP_FILES_AND_FOLDERS_ROOT => Parent::Root,
+            id => Parent::Folder(id),
+        };
+
+        Ok((storage_id, parent))
+    }
+
+    /// Moves the object to the given parent folder of the writable storage with the most free
+    /// space (see `StoragePool::storage_with_most_free_space`), as long as the object fits there.
+    /// Note that the storage pool must be updated first with `MtpDevice::update_storage` and
+    /// `parent` should exist in the selected storage, `Parent::Root` is always a safe choice.
+    ///
+    /// Returns an `Error::MtpError` of kind `MtpErrorKind::StorageFull` if no storage can fit
+    /// the object.
+    fn move_to_best_storage(&self, parent: Parent) -> Result<()> {
+        let size = self.get_u64(Property::ObjectSize)?;
+        let storage_id = best_storage_for(self.device(), size)?;
+
+        self.move_to(storage_id, parent)
+    }
+
     /// Copies the object to the specified storage (by its id) and parent folder. Copying objects
     /// may or not be supported on the device.
     ///
@@ -278,34 +379,217 @@ pub trait Object {
         }
     }
 
-    /// Get partial data from an object, specifying an offset and the maximum bytes
-    /// that should be read. Note that this may return fewer bytes than the maximum.
+    /// Retrieves this object from the device and calls `handler` with chunks of data, with the
+    /// same semantics of `Storage::get_file_to_handler`. Unlike that method, this one tells you
+    /// whether the whole object was retrieved or `handler` cancelled the transfer by returning
+    /// `HandlerReturn::Cancel`.
+    fn get_to_handler<H>(&self, handler: H) -> Result<TransferOutcome>
+    where
+        H: FnMut(&[u8]) -> HandlerReturn,
+    {
+        files::get_file_to_handler(self.device(), self.id(), handler)
+    }
+
+    /// Retrieves the whole object into memory, unless it's larger than `max` bytes, in which case
+    /// returns `Error::ObjectTooLarge` without downloading it. If the device doesn't report the
+    /// size of the object, the transfer is cancelled as soon as more than `max` bytes arrive.
+    fn get_bytes_capped(&self, max: usize) -> Result<Vec<u8>> {
+        let max_size = max as u64;
+
+        let mut bytes = match self.get_u64(Property::ObjectSize) {
+            Ok(size) if size > max_size => {
+                return Err(Error::ObjectTooLarge {
+                    size,
+                    max: max_size,
+                })
+            }
+
+            Ok(size) => Vec::with_capacity(size as usize),
+            Err(_) => Vec::new(),
+        };
+
+        let mut exceeded = None;
+        // The handler only cancels the transfer once the cap is exceeded.
+        self.get_to_handler(|data| {
+            let received = bytes.len() + data.len();
+
+            if received > max {
+                exceeded = Some(received as u64);
+                HandlerReturn::Cancel
+            } else {
+                bytes.extend_from_slice(data);
+                HandlerReturn::Ok(data.len() as u32)
+            }
+        })?;
+
+        match exceeded {
+            Some(size) => Err(Error::ObjectTooLarge {
+                size,
+                max: max_size,
+            }),
+
+            None => Ok(bytes),
+        }
+    }
+
+    /// Same as [`move_to_best_storage`](#method.move_to_best_storage), but copies the object
+    /// instead of moving it.
+    fn copy_to_best_storage(&self, parent: Parent) -> Result<()> {
+        let size = self.get_u64(Property::ObjectSize)?;
+        let storage_id = best_storage_for(self.device(), size)?;
+
+        self.copy_to(storage_id, parent)
+    }
+
+    /// Get partial data from an object, specifying an offset and the maximum bytes that should
+    /// be read. Devices may return fewer bytes per request than asked for (due to their maximum
+    /// transfer size), so this issues as many requests as needed to read `maxbytes`, fewer bytes
+    /// are only returned if the end of the object is reached.
     fn get_partial_object(&self, offset: u64, maxbytes: u32) -> Result<Vec<u8>> {
-        let id = self.id();
         let device = self.device();
 
-        let mut size = 0;
-        let mut data = std::ptr::null_mut();
-
-        let res = unsafe {
-            ffi::LIBMTP_GetPartialObject(device.inner, id, offset, maxbytes, &mut data, &mut size)
+        // libmtp doesn't expose the maximum transfer size, instead we rely on the size of the
+        // object to know whether a short read is the end of it (if unknown, do a single request).
+        let maxbytes = match self.get_u64(Property::ObjectSize) {
+            Ok(size) if size > 0 => size.saturating_sub(offset).min(maxbytes as u64) as u32,
+            _ => return get_partial_object_chunk(device, self.id(), offset, maxbytes),
         };
 
-        if res != 0 || data.is_null() {
-            if !data.is_null() {
-                unsafe {
-                    libc::free(data as *mut _);
-                }
+        let mut bytes = Vec::with_capacity(maxbytes as usize);
+        while bytes.len() < maxbytes as usize {
+            let remaining = maxbytes - bytes.len() as u32;
+            let chunk = get_partial_object_chunk(
+                device,
+                self.id(),
+                offset + bytes.len() as u64,
+                remaining,
+            )?;
+
+            if chunk.is_empty() {
+                break;
             }
 
+            bytes.extend_from_slice(&chunk);
+        }
+
+        Ok(bytes)
+    }
+
+    /// Reads at most `max_len` bytes of this object starting at `offset` with a single request,
+    /// the result contains exactly the bytes the device returned, which may be fewer than
+    /// `max_len` (e.g. near the end of the object). Returns `Error::UnsupportedCapability` if the
+    /// device doesn't support `DeviceCapability::GetPartialObject`.
+    ///
+    /// Use [`get_partial_object`](#method.get_partial_object) to read the whole range.
+    fn get_partial(&self, offset: u64, max_len: u32) -> Result<Vec<u8>> {
+        let device = self.device();
+
+        if !device.check_capability(DeviceCapability::GetPartialObject) {
+            return Err(Error::UnsupportedCapability {
+                capability: DeviceCapability::GetPartialObject,
+            });
+        }
+
+        get_partial_object_chunk(device, self.id(), offset, max_len)
+    }
+
+    /// Writes `data` into this object starting at `offset`, an empty `data` is a no-op. Returns
+    /// `Error::UnsupportedCapability` if the device doesn't support
+    /// `DeviceCapability::SendPartialObject`, errors of the device (e.g. writing beyond the size
+    /// of the object) are returned as is.
+    ///
+    /// This requires the device to support in place edits, some devices also need the write to
+    /// happen inside an edit session, see
+    /// [`File::open_edit`](../storage/files/struct.File.html#method.open_edit).
+    fn send_partial(&self, offset: u64, data: &[u8]) -> Result<()> {
+        if data.is_empty() {
+            return Ok(());
+        }
+
+        if !self
+            .device()
+            .check_capability(DeviceCapability::SendPartialObject)
+        {
+            return Err(Error::UnsupportedCapability {
+                capability: DeviceCapability::SendPartialObject,
+            });
+        }
+
+        self.send_partial_object(offset, data)
+    }
+
+    /// Retrieves the representative sample of this object (e.g. the cover art of an `Album`),
+    /// see `SampleData`.
+    fn get_representative_sample(&self) -> Result<SampleData> {
+        let device = self.device();
+
+        unsafe {
+            let sample = ffi::LIBMTP_new_filesampledata_t();
+            let res = ffi::LIBMTP_Get_Representative_Sample(device.inner, self.id(), sample);
+
+            let result = if res != 0 {
+                Err(device.latest_error().unwrap_or_default())
+            } else {
+                Ok(SampleData::from_raw(sample))
+            };
+
+            ffi::LIBMTP_destroy_filesampledata_t(sample);
+            result
+        }
+    }
+
+    /// Sets the representative sample of this object (e.g. the cover art of an `Album`), check
+    /// the requirements of the device with `MtpDevice::sample_format_requirements` first.
+    fn set_representative_sample(&self, sample: &SampleData) -> Result<()> {
+        let device = self.device();
+
+        unsafe {
+            let sample = sample.to_raw();
+            let res = ffi::LIBMTP_Send_Representative_Sample(device.inner, self.id(), sample);
+            ffi::LIBMTP_destroy_filesampledata_t(sample);
+
+            if res != 0 {
+                Err(device.latest_error().unwrap_or_default())
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    /// Truncates this object to `new_length` bytes, inside its own edit session. Returns
+    /// `Error::UnsupportedCapability` if the device doesn't support
+    /// `DeviceCapability::EditObjects`.
+    ///
+    /// Note that metadata already retrieved (e.g. the size of a `File`) isn't updated, retrieve
+    /// it again or use [`File::open_edit`](../storage/files/struct.File.html#method.open_edit).
+    fn truncate(&self, new_length: u64) -> Result<()> {
+        let id = self.id();
+        let device = self.device();
+
+        if !device.check_capability(DeviceCapability::EditObjects) {
+            return Err(Error::UnsupportedCapability {
+                capability: DeviceCapability::EditObjects,
+            });
+        }
+
+        if unsafe { ffi::LIBMTP_BeginEditObject(device.inner, id) } != 0 {
+            return Err(device.latest_error().unwrap_or_default());
+        }
+
+        let truncated = unsafe { ffi::LIBMTP_TruncateObject(device.inner, id, new_length) };
+        let truncated = if truncated != 0 {
             Err(device.latest_error().unwrap_or_default())
         } else {
-            let bytes = unsafe { prim_array_ptr_to_vec!(data, u8, size) };
-            unsafe {
-                libc::free(data as *mut _);
-            }
+            Ok(())
+        };
+
+        let ended = unsafe { ffi::LIBMTP_EndEditObject(device.inner, id) };
 
-            Ok(bytes)
+        truncated?;
+        if ended != 0 {
+            Err(device.latest_error().unwrap_or_default())
+        } else {
+            Ok(())
         }
     }
 
@@ -335,3 +619,116 @@ pub trait Object {
         }
     }
 }
+
+/// Retrieves the raw bytes of a string attribute, `None` if the attribute is unset.
+fn get_raw_string<O>(object: &O, property: Property) -> Result<Option<Vec<u8>>>
+where
+    O: Object + ?Sized,
+{
+    let property = property.to_u32().unwrap();
+    let id = object.id();
+    let device = object.device();
+
+    device.clear_errors();
+    let string = unsafe { ffi::LIBMTP_Get_String_From_Object(device.inner, id, property) };
+
+    if string.is_null() {
+        if let Some(err) = device.latest_error() {
+            Err(err)
+        } else {
+            Ok(None)
+        }
+    } else {
+        unsafe {
+            let u8vec = cstr_to_u8vec!(string);
+            libc::free(string as *mut _);
+            Ok(Some(u8vec))
+        }
+    }
+}
+
+/// Performs a single `GetPartialObject` request, the device may return fewer bytes than `maxbytes`.
+fn get_partial_object_chunk(
+    device: &MtpDevice,
+    id: u32,
+    offset: u64,
+    maxbytes: u32,
+) -> Result<Vec<u8>> {
+    let mut size = 0;
+    let mut data = std::ptr::null_mut();
+
+    let res = unsafe {
+        ffi::LIBMTP_GetPartialObject(device.inner, id, offset, maxbytes, &mut data, &mut size)
+    };
+
+    if res != 0 || data.is_null() {
+        if !data.is_null() {
+            unsafe {
+                libc::free(data as *mut _);
+            }
+        }
+
+        Err(device.latest_error().unwrap_or_default())
+    } else {
+        let bytes = unsafe { prim_array_ptr_to_vec!(data, u8, size) };
+        unsafe {
+            libc::free(data as *mut _);
+        }
+
+        Ok(bytes)
+    }
+}
+
+/// Returns the id of the writable storage with the most free space, if it can fit `size` bytes.
+fn best_storage_for(device: &MtpDevice, size: u64) -> Result<u32> {
+    device
+        .storage_pool()
+        .storage_with_most_free_space()
+        .filter(|storage| storage.free_space_in_bytes() >= size)
+        .map(|storage| storage.id())
+        .ok_or_else(|| Error::MtpError {
+            kind: MtpErrorKind::StorageFull,
+            text: "No storage has enough free space for the object".to_string(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::raw::detect_raw_devices;
+    use crate::device::StorageSort;
+    use crate::object::filetypes::Filetype;
+    use crate::storage::files::FileMetadata;
+    use crate::util::CallbackReturn;
+    use chrono::Utc;
+
+    #[test]
+    #[ignore = "needs a connected MTP device supporting GetPartialObject, with a writable storage"]
+    fn reads_the_start_of_a_sent_file() {
+        let raw = detect_raw_devices().unwrap().remove(0);
+        let mut device = raw.try_open_uncached().unwrap();
+        device.update_storage(StorageSort::ByFreeSpace).unwrap();
+
+        let data: Vec<u8> = (0..=255).collect();
+        let path = std::env::temp_dir().join(format!("libmtp-rs-partial-{}", std::process::id()));
+        std::fs::write(&path, &data).unwrap();
+
+        let pool = device.storage_pool();
+        let (_, storage) = pool.iter().find(|(_, s)| s.is_writable()).unwrap();
+        let metadata = FileMetadata {
+            file_size: data.len() as u64,
+            file_name: "libmtp-rs-partial.bin",
+            file_type: Filetype::Unknown,
+            modification_date: Utc::now(),
+        };
+
+        let file = storage
+            .send_file_from_path::<fn(u64, u64) -> CallbackReturn>(&path, Parent::Root, metadata)
+            .unwrap();
+        std::fs::remove_file(path).unwrap();
+
+        let start = file.get_partial(0, 64);
+        file.delete().unwrap();
+        assert_eq!(start.unwrap(), data[..64]);
+    }
+}
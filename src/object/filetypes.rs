@@ -9,7 +9,7 @@ use std::fmt::{self, Display};
 
 /// Enumeration that holds the supported filetypes, this enum implements `Display`
 /// with the description of the file type.
-#[derive(Debug, Clone, FromPrimitive, ToPrimitive)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, FromPrimitive, ToPrimitive)]
 pub enum Filetype {
     Folder = 0,
     Wav,
@@ -58,6 +58,243 @@ pub enum Filetype {
     Unknown,
 }
 
+/// Broad category of a file type, see `Filetype::category`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FiletypeCategory {
+    Audio,
+    Video,
+    Image,
+    Document,
+    /// Objects that group other objects: folders, albums and playlists.
+    Container,
+    Other,
+}
+
+impl Filetype {
+    /// Returns the broad category of this file type.
+    pub fn category(&self) -> FiletypeCategory {
+        match self {
+            Filetype::Wav
+            | Filetype::Mp3
+            | Filetype::Wma
+            | Filetype::Ogg
+            | Filetype::Audible
+            | Filetype::UndefAudio
+            | Filetype::Aac
+            | Filetype::Flac
+            | Filetype::Mp2
+            | Filetype::M4a => FiletypeCategory::Audio,
+
+            Filetype::Mp4
+            | Filetype::Wmv
+            | Filetype::Avi
+            | Filetype::Mpeg
+            | Filetype::Asf
+            | Filetype::Qt
+            | Filetype::UndefVideo => FiletypeCategory::Video,
+
+            Filetype::Jpeg
+            | Filetype::Jfif
+            | Filetype::Tiff
+            | Filetype::Bmp
+            | Filetype::Gif
+            | Filetype::Pict
+            | Filetype::Png
+            | Filetype::WindowsImageFormat
+            | Filetype::Jp2
+            | Filetype::Jpx => FiletypeCategory::Image,
+
+            Filetype::Text
+            | Filetype::Html
+            | Filetype::Doc
+            | Filetype::Xml
+            | Filetype::Xls
+            | Filetype::Ppt
+            | Filetype::Mht => FiletypeCategory::Document,
+
+            Filetype::Folder | Filetype::Album | Filetype::Playlist => FiletypeCategory::Container,
+
+            Filetype::VCalendar1
+            | Filetype::VCalendar2
+            | Filetype::VCard2
+            | Filetype::VCard3
+            | Filetype::WinExec
+            | Filetype::Firmware
+            | Filetype::MediaCard
+            | Filetype::Unknown => FiletypeCategory::Other,
+        }
+    }
+
+    /// Whether this file type is an audio format.
+    pub fn is_audio(&self) -> bool {
+        self.category() == FiletypeCategory::Audio
+    }
+
+    /// Whether this file type is a video format.
+    pub fn is_video(&self) -> bool {
+        self.category() == FiletypeCategory::Video
+    }
+
+    /// Whether this file type is an image format.
+    pub fn is_image(&self) -> bool {
+        self.category() == FiletypeCategory::Image
+    }
+
+    /// Returns the canonical MIME type of this file type, abstract or generic types (like
+    /// `Folder`, `Album`, `UndefAudio` or `Unknown`) don't have one.
+    pub fn mime_type(&self) -> Option<&'static str> {
+        let mime = match self {
+            Filetype::Wav => "audio/wav",
+            Filetype::Mp3 => "audio/mpeg",
+            Filetype::Wma => "audio/x-ms-wma",
+            Filetype::Ogg => "audio/ogg",
+            Filetype::Audible => "audio/audible",
+            Filetype::Mp4 => "video/mp4",
+            Filetype::Wmv => "video/x-ms-wmv",
+            Filetype::Avi => "video/x-msvideo",
+            Filetype::Mpeg => "video/mpeg",
+            Filetype::Asf => "video/x-ms-asf",
+            Filetype::Qt => "video/quicktime",
+            Filetype::Jpeg => "image/jpeg",
+            Filetype::Jfif => "image/jpeg",
+            Filetype::Tiff => "image/tiff",
+            Filetype::Bmp => "image/bmp",
+            Filetype::Gif => "image/gif",
+            Filetype::Pict => "image/x-pict",
+            Filetype::Png => "image/png",
+            Filetype::VCalendar1 => "text/x-vcalendar",
+            Filetype::VCalendar2 => "text/calendar",
+            Filetype::VCard2 => "text/x-vcard",
+            Filetype::VCard3 => "text/vcard",
+            Filetype::WinExec => "application/x-msdownload",
+            Filetype::Text => "text/plain",
+            Filetype::Html => "text/html",
+            Filetype::Firmware => "application/octet-stream",
+            Filetype::Aac => "audio/aac",
+            Filetype::Flac => "audio/flac",
+            Filetype::Mp2 => "audio/mpeg",
+            Filetype::M4a => "audio/mp4",
+            Filetype::Doc => "application/msword",
+            Filetype::Xml => "application/xml",
+            Filetype::Xls => "application/vnd.ms-excel",
+            Filetype::Ppt => "application/vnd.ms-powerpoint",
+            Filetype::Mht => "multipart/related",
+            Filetype::Jp2 => "image/jp2",
+            Filetype::Jpx => "image/jpx",
+            Filetype::Folder
+            | Filetype::UndefAudio
+            | Filetype::UndefVideo
+            | Filetype::WindowsImageFormat
+            | Filetype::MediaCard
+            | Filetype::Album
+            | Filetype::Playlist
+            | Filetype::Unknown => return None,
+        };
+
+        Some(mime)
+    }
+
+    /// Returns the usual file extension (without the dot) of this file type, abstract or
+    /// generic types (like `Folder`, `Album`, `Playlist` or `Unknown`) don't have one.
+    pub fn extension(&self) -> Option<&'static str> {
+        let ext = match self {
+            Filetype::Wav => "wav",
+            Filetype::Mp3 => "mp3",
+            Filetype::Wma => "wma",
+            Filetype::Ogg => "ogg",
+            Filetype::Audible => "aa",
+            Filetype::Mp4 => "mp4",
+            Filetype::Wmv => "wmv",
+            Filetype::Avi => "avi",
+            Filetype::Mpeg => "mpeg",
+            Filetype::Asf => "asf",
+            Filetype::Qt => "mov",
+            Filetype::Jpeg => "jpg",
+            Filetype::Jfif => "jfif",
+            Filetype::Tiff => "tiff",
+            Filetype::Bmp => "bmp",
+            Filetype::Gif => "gif",
+            Filetype::Pict => "pict",
+            Filetype::Png => "png",
+            Filetype::VCalendar1 => "vcs",
+            Filetype::VCalendar2 => "ics",
+            Filetype::VCard2 | Filetype::VCard3 => "vcf",
+            Filetype::WinExec => "exe",
+            Filetype::Text => "txt",
+            Filetype::Html => "html",
+            Filetype::Firmware => "bin",
+            Filetype::Aac => "aac",
+            Filetype::Flac => "flac",
+            Filetype::Mp2 => "mp2",
+            Filetype::M4a => "m4a",
+            Filetype::Doc => "doc",
+            Filetype::Xml => "xml",
+            Filetype::Xls => "xls",
+            Filetype::Ppt => "ppt",
+            Filetype::Mht => "mht",
+            Filetype::Jp2 => "jp2",
+            Filetype::Jpx => "jpx",
+            Filetype::Folder
+            | Filetype::UndefAudio
+            | Filetype::UndefVideo
+            | Filetype::WindowsImageFormat
+            | Filetype::MediaCard
+            | Filetype::Album
+            | Filetype::Playlist
+            | Filetype::Unknown => return None,
+        };
+
+        Some(ext)
+    }
+
+    /// Guesses the file type from a file extension (without the dot, case insensitive), returns
+    /// `None` if the extension isn't known. File types that share an extension (like `VCard2` and
+    /// `VCard3`) are guessed as the newest one.
+    pub fn from_extension(ext: &str) -> Option<Filetype> {
+        let ftype = match ext.to_ascii_lowercase().as_str() {
+            "wav" => Filetype::Wav,
+            "mp3" => Filetype::Mp3,
+            "wma" => Filetype::Wma,
+            "ogg" | "oga" => Filetype::Ogg,
+            "aa" | "aax" => Filetype::Audible,
+            "mp4" | "m4v" => Filetype::Mp4,
+            "wmv" => Filetype::Wmv,
+            "avi" => Filetype::Avi,
+            "mpeg" | "mpg" => Filetype::Mpeg,
+            "asf" => Filetype::Asf,
+            "mov" | "qt" => Filetype::Qt,
+            "jpg" | "jpeg" => Filetype::Jpeg,
+            "jfif" => Filetype::Jfif,
+            "tif" | "tiff" => Filetype::Tiff,
+            "bmp" => Filetype::Bmp,
+            "gif" => Filetype::Gif,
+            "pict" | "pct" => Filetype::Pict,
+            "png" => Filetype::Png,
+            "vcs" => Filetype::VCalendar1,
+            "ics" => Filetype::VCalendar2,
+            "vcf" => Filetype::VCard3,
+            "exe" => Filetype::WinExec,
+            "txt" => Filetype::Text,
+            "htm" | "html" => Filetype::Html,
+            "bin" => Filetype::Firmware,
+            "aac" => Filetype::Aac,
+            "flac" => Filetype::Flac,
+            "mp2" => Filetype::Mp2,
+            "m4a" => Filetype::M4a,
+            "doc" => Filetype::Doc,
+            "xml" => Filetype::Xml,
+            "xls" => Filetype::Xls,
+            "ppt" => Filetype::Ppt,
+            "mht" | "mhtml" => Filetype::Mht,
+            "jp2" => Filetype::Jp2,
+            "jpx" => Filetype::Jpx,
+            _ => return None,
+        };
+
+        Some(ftype)
+    }
+}
+
 impl Display for Filetype {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         let ftype = self.to_u32().unwrap();
@@ -70,3 +307,99 @@ impl Display for Filetype {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_traits::FromPrimitive;
+
+    fn all_filetypes() -> impl Iterator<Item = Filetype> {
+        (0..=Filetype::Unknown.to_u32().unwrap()).map(|code| Filetype::from_u32(code).unwrap())
+    }
+
+    #[test]
+    fn parses_extensions_case_insensitively() {
+        assert_eq!(Filetype::from_extension("mp3"), Some(Filetype::Mp3));
+        assert_eq!(Filetype::from_extension("MP3"), Some(Filetype::Mp3));
+        assert_eq!(Filetype::from_extension("Jpeg"), Some(Filetype::Jpeg));
+        assert_eq!(Filetype::from_extension("FLAC"), Some(Filetype::Flac));
+        assert_eq!(Filetype::from_extension("m4a"), Some(Filetype::M4a));
+        assert_eq!(Filetype::from_extension("nope"), None);
+        assert_eq!(Filetype::from_extension(""), None);
+    }
+
+    #[test]
+    fn abstract_filetypes_have_no_extension() {
+        assert_eq!(Filetype::Folder.extension(), None);
+        assert_eq!(Filetype::Album.extension(), None);
+        assert_eq!(Filetype::Playlist.extension(), None);
+        assert_eq!(Filetype::Unknown.extension(), None);
+    }
+
+    #[test]
+    fn extensions_round_trip() {
+        for ftype in all_filetypes().filter(|ftype| *ftype != Filetype::VCard2) {
+            if let Some(ext) = ftype.extension() {
+                assert_eq!(Filetype::from_extension(ext), Some(ftype));
+            }
+        }
+
+        // Shares its extension with `VCard3`, which is the one guessed.
+        assert_eq!(Filetype::VCard2.extension(), Some("vcf"));
+        assert_eq!(Filetype::from_extension("vcf"), Some(Filetype::VCard3));
+    }
+
+    #[test]
+    fn categories() {
+        assert_eq!(Filetype::Mp3.category(), FiletypeCategory::Audio);
+        assert_eq!(Filetype::Flac.category(), FiletypeCategory::Audio);
+        assert_eq!(Filetype::Mp4.category(), FiletypeCategory::Video);
+        assert_eq!(Filetype::Avi.category(), FiletypeCategory::Video);
+        assert_eq!(Filetype::Jpeg.category(), FiletypeCategory::Image);
+        assert_eq!(Filetype::Png.category(), FiletypeCategory::Image);
+        assert_eq!(Filetype::Folder.category(), FiletypeCategory::Container);
+        assert_eq!(Filetype::Album.category(), FiletypeCategory::Container);
+        assert_eq!(Filetype::Text.category(), FiletypeCategory::Document);
+        assert_eq!(Filetype::Unknown.category(), FiletypeCategory::Other);
+    }
+
+    #[test]
+    fn category_predicates() {
+        assert!(Filetype::Mp3.is_audio());
+        assert!(!Filetype::Mp3.is_video());
+        assert!(Filetype::Avi.is_video());
+        assert!(!Filetype::Avi.is_image());
+        assert!(Filetype::Png.is_image());
+        assert!(!Filetype::Png.is_audio());
+        assert!(!Filetype::Folder.is_audio());
+    }
+
+    #[test]
+    fn mime_types() {
+        assert_eq!(Filetype::Mp3.mime_type(), Some("audio/mpeg"));
+        assert_eq!(Filetype::Flac.mime_type(), Some("audio/flac"));
+        assert_eq!(Filetype::Jpeg.mime_type(), Some("image/jpeg"));
+        assert_eq!(Filetype::Png.mime_type(), Some("image/png"));
+        assert_eq!(Filetype::Mp4.mime_type(), Some("video/mp4"));
+        assert_eq!(Filetype::Text.mime_type(), Some("text/plain"));
+
+        assert_eq!(Filetype::Folder.mime_type(), None);
+        assert_eq!(Filetype::Unknown.mime_type(), None);
+    }
+
+    #[test]
+    fn mime_types_match_categories() {
+        for ftype in all_filetypes() {
+            let expected = match ftype.category() {
+                FiletypeCategory::Audio => "audio/",
+                FiletypeCategory::Video => "video/",
+                FiletypeCategory::Image => "image/",
+                _ => continue,
+            };
+
+            if let Some(mime) = ftype.mime_type() {
+                assert!(mime.starts_with(expected), "{:?} is {}", ftype, mime);
+            }
+        }
+    }
+}
@@ -8,9 +8,11 @@ use num_traits::ToPrimitive;
 use std::ffi::CStr;
 use std::fmt::{self, Display};
 
+use crate::values::DataType;
+
 /// Enumeration that holds the supported properties, this enum implements `Display` with the
 /// description of the property.
-#[derive(Debug, Clone, Copy, FromPrimitive, ToPrimitive)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive, ToPrimitive)]
 pub enum Property {
     StorageId = 0,
     ObjectFormat,
@@ -172,6 +174,7 @@ pub enum Property {
     AudioWaveCodec,
     AudioBitRate,
     VideoFourCCCodec,
+    VideoBitRate,
     FramesPerThousandSeconds,
     KeyFrameDistance,
     BufferSize,
@@ -181,14 +184,202 @@ pub enum Property {
     Unknown,
 }
 
-impl Display for Property {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+// The variants follow the order of `LIBMTP_property_t`, a missing variant would shift the rest.
+const _: () = assert!(Property::Unknown as u32 == ffi::LIBMTP_property_t_LIBMTP_PROPERTY_UNKNOWN);
+
+impl Property {
+    /// Returns the description `libmtp` gives to this property (e.g. `"Object Filename"`).
+    pub fn description(&self) -> &'static str {
         let ptype = self.to_u32().expect("Unexpected Property variant?");
+
         unsafe {
             let desc = ffi::LIBMTP_Get_Property_Description(ptype);
-            let cstr = CStr::from_ptr(desc);
 
-            write!(f, "{}", cstr.to_str().unwrap())
+            if desc.is_null() {
+                "Unknown"
+            } else {
+                CStr::from_ptr(desc).to_str().unwrap_or("Unknown")
+            }
+        }
+    }
+
+    /// Returns the data type the MTP specification defines for the values of this property,
+    /// `None` for string properties and for those whose values are arrays or aren't known.
+    pub fn datatype(&self) -> Option<DataType> {
+        use Property::*;
+
+        match self {
+            NonConsumable | CorruptOrUnplayable | BuyFlag => Some(DataType::U8),
+
+            ObjectFormat
+            | ProtectionStatus
+            | AssociationType
+            | Hidden
+            | SystemObject
+            | RepresentativeSampleFormat
+            | Rating
+            | Track
+            | MetaGenre
+            | EffectiveRating
+            | DrmStatus
+            | IsCropped
+            | IsColorCorrected
+            | Fnumber
+            | ExposureIndex
+            | Priority
+            | MessageRead
+            | BitRateType
+            | NumberOfChannels
+            | ScanDepth => Some(DataType::U16),
+
+            StorageId
+            | AssociationDesc
+            | ParentObject
+            | RepresentativeSampleSize
+            | RepresentativeSampleHeight
+            | RepresentativeSampleWidth
+            | RepresentativeSampleDuration
+            | Width
+            | Height
+            | Duration
+            | UseCount
+            | SkipCount
+            | ImageBitDepth
+            | ExposureTime
+            | TimeBookmark
+            | ObjectBookmark
+            | TotalBitRate
+            | SampleRate
+            | AudioBitDepth
+            | AudioWaveCodec
+            | AudioBitRate
+            | VideoFourCCCodec
+            | VideoBitRate
+            | FramesPerThousandSeconds
+            | KeyFrameDistance
+            | BufferSize
+            | EncodingQuality => Some(DataType::U32),
+
+            ObjectSize | ByteBookmark => Some(DataType::U64),
+
+            _ => None,
         }
     }
+
+    /// Whether the values of this property are strings, according to the MTP specification.
+    pub fn is_string(&self) -> bool {
+        use Property::*;
+
+        match self {
+            _ if self.datatype().is_some() => false,
+
+            AllowedFolderContents
+            | PersistantUniqueObjectIdentifier
+            | PropertyBag
+            | Description
+            | CopyrightInformation
+            | RepresentativeSampleData
+            | Credits
+            | Lyrics
+            | SubDescription
+            | BodyText
+            | ActivityAccepted
+            | TimeToLive
+            | Unknown => false,
+
+            _ => true,
+        }
+    }
+}
+
+impl Display for Property {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.description())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use num_traits::FromPrimitive;
+
+    #[test]
+    fn codes_round_trip() {
+        let sample = [
+            (
+                Property::StorageId,
+                ffi::LIBMTP_property_t_LIBMTP_PROPERTY_StorageID,
+            ),
+            (
+                Property::ObjectSize,
+                ffi::LIBMTP_property_t_LIBMTP_PROPERTY_ObjectSize,
+            ),
+            (
+                Property::ObjectFileName,
+                ffi::LIBMTP_property_t_LIBMTP_PROPERTY_ObjectFileName,
+            ),
+            (
+                Property::DateModified,
+                ffi::LIBMTP_property_t_LIBMTP_PROPERTY_DateModified,
+            ),
+            (Property::Name, ffi::LIBMTP_property_t_LIBMTP_PROPERTY_Name),
+            (
+                Property::Artist,
+                ffi::LIBMTP_property_t_LIBMTP_PROPERTY_Artist,
+            ),
+            (
+                Property::Duration,
+                ffi::LIBMTP_property_t_LIBMTP_PROPERTY_Duration,
+            ),
+            (
+                Property::Rating,
+                ffi::LIBMTP_property_t_LIBMTP_PROPERTY_Rating,
+            ),
+            (
+                Property::AlbumName,
+                ffi::LIBMTP_property_t_LIBMTP_PROPERTY_AlbumName,
+            ),
+            (
+                Property::VideoBitRate,
+                ffi::LIBMTP_property_t_LIBMTP_PROPERTY_VideoBitRate,
+            ),
+            (
+                Property::BuyFlag,
+                ffi::LIBMTP_property_t_LIBMTP_PROPERTY_BuyFlag,
+            ),
+            (
+                Property::Unknown,
+                ffi::LIBMTP_property_t_LIBMTP_PROPERTY_UNKNOWN,
+            ),
+        ];
+
+        for (property, code) in sample.iter().copied() {
+            assert_eq!(property.to_u32(), Some(code), "{:?}", property);
+            assert_eq!(Property::from_u32(code), Some(property));
+        }
+    }
+
+    #[test]
+    fn known_datatypes() {
+        assert_eq!(Property::StorageId.datatype(), Some(DataType::U32));
+        assert_eq!(Property::ObjectFormat.datatype(), Some(DataType::U16));
+        assert_eq!(Property::ObjectSize.datatype(), Some(DataType::U64));
+        assert_eq!(Property::Duration.datatype(), Some(DataType::U32));
+        assert_eq!(Property::Rating.datatype(), Some(DataType::U16));
+        assert_eq!(Property::BuyFlag.datatype(), Some(DataType::U8));
+        assert_eq!(Property::ObjectFileName.datatype(), None);
+        assert_eq!(Property::Unknown.datatype(), None);
+    }
+
+    #[test]
+    fn string_properties() {
+        assert!(Property::ObjectFileName.is_string());
+        assert!(Property::Name.is_string());
+        assert!(Property::Artist.is_string());
+        assert!(Property::DateModified.is_string());
+
+        assert!(!Property::ObjectSize.is_string());
+        assert!(!Property::RepresentativeSampleData.is_string());
+        assert!(!Property::Unknown.is_string());
+    }
 }
@@ -0,0 +1,96 @@
+//! Contains items to handle representative samples, these are small pieces of data attached to
+//! an object that represent it, e.g. the cover art of an album or the thumbnail of a video.
+
+use libmtp_sys as ffi;
+use num_traits::{FromPrimitive, ToPrimitive};
+
+use crate::object::filetypes::Filetype;
+
+/// Representative sample of an object (e.g. the cover art of an album), see
+/// `Object::get_representative_sample` and `Object::set_representative_sample`.
+#[derive(Debug, Clone)]
+pub struct SampleData {
+    pub filetype: Filetype,
+    /// Width of the sample if it's an image.
+    pub width: u32,
+    /// Height of the sample if it's an image.
+    pub height: u32,
+    /// Duration in milliseconds if it's audio.
+    pub duration: u32,
+    pub data: Vec<u8>,
+}
+
+/// Requirements of the representative samples of a certain filetype, see
+/// `MtpDevice::sample_format_requirements`.
+#[derive(Debug, Clone)]
+pub struct SampleFormat {
+    pub filetype: Filetype,
+    /// Maximum width of the sample if it's an image.
+    pub width: u32,
+    /// Maximum height of the sample if it's an image.
+    pub height: u32,
+    /// Maximum duration in milliseconds if it's audio.
+    pub duration: u32,
+    /// Maximum size of the sample in bytes.
+    pub max_size: u64,
+}
+
+fn filetype_from_raw(filetype: ffi::LIBMTP_filetype_t) -> Filetype {
+    Filetype::from_u32(filetype).unwrap_or(Filetype::Unknown)
+}
+
+impl SampleData {
+    /// Builds the sample from a `LIBMTP_filesampledata_t`, doesn't take ownership of it.
+    pub(crate) unsafe fn from_raw(sample: *const ffi::LIBMTP_filesampledata_t) -> Self {
+        let data = if (*sample).data.is_null() || (*sample).size == 0 {
+            Vec::new()
+        } else {
+            prim_array_ptr_to_vec!((*sample).data, u8, (*sample).size)
+        };
+
+        SampleData {
+            filetype: filetype_from_raw((*sample).filetype),
+            width: (*sample).width,
+            height: (*sample).height,
+            duration: (*sample).duration,
+            data,
+        }
+    }
+
+    /// Builds a new `LIBMTP_filesampledata_t` with a copy of this sample, it must be destroyed
+    /// with `LIBMTP_destroy_filesampledata_t`.
+    pub(crate) unsafe fn to_raw(&self) -> *mut ffi::LIBMTP_filesampledata_t {
+        let sample = ffi::LIBMTP_new_filesampledata_t();
+
+        (*sample).filetype = self
+            .filetype
+            .to_u32()
+            .expect("Unexpected variant in Filetype");
+        (*sample).width = self.width;
+        (*sample).height = self.height;
+        (*sample).duration = self.duration;
+
+        if !self.data.is_empty() {
+            let data = libc::malloc(self.data.len()) as *mut libc::c_char;
+            std::ptr::copy_nonoverlapping(self.data.as_ptr() as *const _, data, self.data.len());
+
+            (*sample).data = data;
+            (*sample).size = self.data.len() as u64;
+        }
+
+        sample
+    }
+}
+
+impl SampleFormat {
+    /// Builds the format from a `LIBMTP_filesampledata_t`, doesn't take ownership of it.
+    pub(crate) unsafe fn from_raw(sample: *const ffi::LIBMTP_filesampledata_t) -> Self {
+        SampleFormat {
+            filetype: filetype_from_raw((*sample).filetype),
+            width: (*sample).width,
+            height: (*sample).height,
+            duration: (*sample).duration,
+            max_size: (*sample).size,
+        }
+    }
+}
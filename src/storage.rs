@@ -2,17 +2,26 @@
 //! an specific device, and perform certain operations like sending and getting
 //! files, tracks, etc.
 
+pub mod albums;
+#[cfg(feature = "async")]
+pub mod asynchronous;
 pub mod files;
 pub mod folders;
+pub mod playlists;
+pub mod tracks;
 
+use albums::{Album, AlbumMetadata};
 use derivative::Derivative;
 use files::{File, FileMetadata};
 use libmtp_sys as ffi;
 use num_derive::FromPrimitive;
 use num_traits::FromPrimitive;
+use playlists::Playlist;
+use tracks::{Track, TrackMetadata};
 
 use std::borrow::Cow;
-use std::collections::HashMap;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
 use std::ffi::CStr;
 use std::fmt::{self, Debug};
 use std::path::Path;
@@ -21,14 +30,18 @@ use std::path::Path;
 use std::os::unix::io::AsRawFd;
 
 use crate::device::MtpDevice;
-use crate::object::AsObjectId;
-use crate::storage::folders::Folder;
-use crate::storage::folders::{create_folder, get_folder_list, get_folder_list_storage};
+use crate::error::{Error, MtpErrorKind};
+use crate::object::filetypes::Filetype;
+use crate::object::{AsObjectId, Object};
+use crate::storage::folders::{
+    create_folder, folder_tree, get_folder_list, get_folder_list_storage,
+};
+use crate::storage::folders::{Folder, FolderNode, FolderTree};
 use crate::util::{CallbackReturn, HandlerReturn};
 use crate::Result;
 
 /// Internal function to retrieve files and folders from a single storage or the whole storage pool.
-fn files_and_folders(mtpdev: &MtpDevice, storage_id: u32, parent: Parent) -> Vec<File> {
+pub(crate) fn files_and_folders(mtpdev: &MtpDevice, storage_id: u32, parent: Parent) -> Vec<File> {
     let parent_id = parent.faf_id();
 
     let mut head =
@@ -47,6 +60,164 @@ fn files_and_folders(mtpdev: &MtpDevice, storage_id: u32, parent: Parent) -> Vec
     files
 }
 
+/// Free space (in bytes) under which a storage is considered full, see `Storage::is_full`.
+pub const FULL_STORAGE_THRESHOLD: u64 = 1024 * 1024;
+
+/// Internal function to resolve a path (components separated by `/`) to the file it points to,
+/// walking the contents of each folder from the root.
+fn resolve_path<'a>(
+    mtpdev: &'a MtpDevice,
+    storage_id: u32,
+    path: &str,
+    case_insensitive: bool,
+) -> Result<Option<File<'a>>> {
+    let same_name = |file: &File, component: &str| {
+        if case_insensitive {
+            file.name().to_lowercase() == component.to_lowercase()
+        } else {
+            file.name() == component
+        }
+    };
+
+    let mut components = path.split('/').filter(|c| !c.is_empty()).peekable();
+    let mut parent = Parent::Root;
+
+    while let Some(component) = components.next() {
+        mtpdev.clear_errors();
+        let contents = files_and_folders(mtpdev, storage_id, parent);
+
+        // An empty listing may also be a failed request, tell them apart by the error stack.
+        if contents.is_empty() {
+            if let Some(err) = mtpdev.latest_error() {
+                return Err(err);
+            }
+        }
+
+        let file = match contents.into_iter().find(|file| same_name(file, component)) {
+            Some(file) => file,
+            None => return Ok(None),
+        };
+
+        if components.peek().is_none() {
+            return Ok(Some(file));
+        }
+
+        match file.ftype() {
+            Filetype::Folder => parent = Parent::Folder(file.id()),
+            _ => return Ok(None),
+        }
+    }
+
+    Ok(None)
+}
+
+/// Internal function to count (recursively) the files under the root of a single storage or the
+/// whole storage pool, stops once `max` files are counted.
+fn count_all_files(mtpdev: &MtpDevice, storage_id: u32, max: Option<usize>) -> usize {
+    let max = max.unwrap_or(usize::MAX);
+    let mut pending = vec![Parent::Root];
+    let mut visited = HashSet::new();
+    let mut count = 0;
+
+    while let Some(parent) = pending.pop() {
+        for file in files_and_folders(mtpdev, storage_id, parent) {
+            if count >= max {
+                return count;
+            }
+
+            match file.ftype() {
+                Filetype::Folder => {
+                    if visited.insert(file.id()) {
+                        pending.push(Parent::Folder(file.id()));
+                    }
+                }
+
+                _ => count += 1,
+            }
+        }
+    }
+
+    count.min(max)
+}
+
+/// Responses of the device refusing to write to a storage (`Store Full`, `Object Write
+/// Protected`, `Store Read Only` and `Access Denied`), as reported by `libmtp` in the text of
+/// the latest error.
+const WRITE_REFUSALS: [&str; 4] = ["Error 200c:", "Error 200d:", "Error 200e:", "Error 200f:"];
+
+/// Tells whether a request failed because the storage is write-protected or full, rather than
+/// because of any other problem (e.g. the device was disconnected).
+fn is_write_refusal(err: &Error) -> bool {
+    match err {
+        Error::MtpError {
+            kind: MtpErrorKind::StorageFull,
+            ..
+        } => true,
+
+        Error::MtpError {
+            kind: MtpErrorKind::PtpLayer,
+            text,
+        } => WRITE_REFUSALS.iter().any(|code| text.starts_with(code)),
+
+        _ => false,
+    }
+}
+
+/// Iterator over every file (folders included) under a parent, depth first, each folder is
+/// yielded before its contents. The contents of a folder are only requested once the iteration
+/// reaches them, and folders that were already visited aren't visited again (in case of buggy
+/// devices reporting cycles). See `Storage::walk`.
+pub struct FileWalker<'a> {
+    owner: &'a MtpDevice,
+    storage_id: u32,
+    next_parent: Option<Parent>,
+    pending: Vec<std::vec::IntoIter<File<'a>>>,
+    visited: HashSet<u32>,
+}
+
+impl<'a> FileWalker<'a> {
+    fn new(owner: &'a MtpDevice, storage_id: u32, root: Parent) -> Self {
+        FileWalker {
+            owner,
+            storage_id,
+            next_parent: Some(root),
+            pending: Vec::new(),
+            visited: HashSet::new(),
+        }
+    }
+}
+
+impl<'a> Iterator for FileWalker<'a> {
+    type Item = File<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(parent) = self.next_parent.take() {
+                let contents = files_and_folders(self.owner, self.storage_id, parent);
+                self.pending.push(contents.into_iter());
+            }
+
+            let level = self.pending.last_mut()?;
+
+            match level.next() {
+                Some(file) => {
+                    if let Filetype::Folder = file.ftype() {
+                        if self.visited.insert(file.id()) {
+                            self.next_parent = Some(Parent::Folder(file.id()));
+                        }
+                    }
+
+                    return Some(file);
+                }
+
+                None => {
+                    self.pending.pop();
+                }
+            }
+        }
+    }
+}
+
 /// Represents the parent folder of an object, the top-most parent is called the "root" as in
 /// *nix like systems.
 #[derive(Debug, Copy, Clone)]
@@ -71,7 +242,43 @@ impl Parent {
     }
 }
 
-#[derive(Debug, Copy, Clone, FromPrimitive)]
+/// Resolves the chain of folders from the root down to `parent` (included) in the given
+/// storage, as pairs of folder id and name, e.g. to show breadcrumbs like `Music > Rock`.
+/// `Parent::Root` resolves to an empty chain, as well as folders that can't be found.
+///
+/// Note that this fetches the folder tree of the storage, consider caching the result.
+pub fn resolve_parent_chain(storage: &Storage, parent: Parent) -> Vec<(u32, String)> {
+    let id = match parent {
+        Parent::Root => return Vec::new(),
+        Parent::Folder(id) => id,
+    };
+
+    let root = match storage.folder_list() {
+        Some(root) => root,
+        None => return Vec::new(),
+    };
+
+    let mut chain = Vec::new();
+    let mut visited = HashSet::new();
+    let mut next = id;
+
+    while next != 0 && next != ffi::LIBMTP_FILES_AND_FOLDERS_ROOT && visited.insert(next) {
+        match root.find(next) {
+            Some(folder) => {
+                chain.push((next, folder.name().to_string()));
+                next = folder.parent_id();
+            }
+
+            None if chain.is_empty() => return Vec::new(),
+            None => break,
+        }
+    }
+
+    chain.reverse();
+    chain
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq, FromPrimitive)]
 pub enum StorageType {
     Undefined = 0,
     FixedRom,
@@ -80,7 +287,7 @@ pub enum StorageType {
     RemovableRam,
 }
 
-#[derive(Debug, Copy, Clone, FromPrimitive)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, FromPrimitive)]
 pub enum FilesystemType {
     Undefined = 0,
     GenericFlat,
@@ -88,13 +295,33 @@ pub enum FilesystemType {
     DesignCameraFilesystem,
 }
 
-#[derive(Debug, Copy, Clone, FromPrimitive)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq, FromPrimitive)]
 pub enum AccessCapability {
     ReadWrite = 0,
     ReadOnly,
     ReadOnlyWithObjectDeletion,
 }
 
+/// Owned copy of the metadata of a `Storage`, taken with
+/// [`Storage::snapshot`](struct.Storage.html#method.snapshot) or
+/// [`StoragePool::snapshot`](struct.StoragePool.html#method.snapshot).
+///
+/// Snapshots are data-only, they aren't tied to the device, so they can be kept across storage
+/// updates (e.g. to compare the free space before and after a transfer), but they can't be used
+/// to perform any I/O.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageSnapshot {
+    pub id: u32,
+    pub storage_type: StorageType,
+    pub filesystem_type: FilesystemType,
+    pub access_capability: AccessCapability,
+    pub maximum_capacity: u64,
+    pub free_space_in_bytes: u64,
+    pub free_space_in_objects: u64,
+    pub description: Option<String>,
+    pub volume_identifier: Option<String>,
+}
+
 /// Storage descriptor of some MTP device, note that updating the storage and
 /// keeping a old copy of this struct is impossible.
 pub struct Storage<'a> {
@@ -141,6 +368,13 @@ impl<'a> Storage<'a> {
         FilesystemType::from_u16(ftype).unwrap_or(FilesystemType::Undefined)
     }
 
+    /// Whether the file system of this storage supports nested folders, storages with a
+    /// `GenericFlat` file system (e.g. some cameras) keep every object at the same level, so
+    /// nesting folders isn't meaningful there.
+    pub fn supports_hierarchy(&self) -> bool {
+        self.filesystem_type() != FilesystemType::GenericFlat
+    }
+
     /// Returns the access capability
     pub fn access_capability(&self) -> AccessCapability {
         let cap = unsafe { (*self.inner).AccessCapability };
@@ -162,30 +396,94 @@ impl<'a> Storage<'a> {
         unsafe { (*self.inner).FreeSpaceInObjects }
     }
 
+    /// Whether this storage accepts writes (`AccessCapability::ReadWrite`), sends to storages
+    /// that don't fail early with `Error::ReadOnlyStorage`.
+    pub fn is_writable(&self) -> bool {
+        self.access_capability() == AccessCapability::ReadWrite
+    }
+
+    /// Whether objects of this storage can be deleted (`AccessCapability::ReadWrite` or
+    /// `AccessCapability::ReadOnlyWithObjectDeletion`).
+    pub fn allows_deletion(&self) -> bool {
+        matches!(
+            self.access_capability(),
+            AccessCapability::ReadWrite | AccessCapability::ReadOnlyWithObjectDeletion
+        )
+    }
+
+    /// Fails with `Error::ReadOnlyStorage` if this storage isn't writable.
+    fn check_writable(&self) -> Result<()> {
+        if self.is_writable() {
+            Ok(())
+        } else {
+            Err(Error::ReadOnlyStorage {
+                storage_id: self.id(),
+            })
+        }
+    }
+
+    /// Returns the used space in bytes (maximum capacity minus free space).
+    pub fn used_space_in_bytes(&self) -> u64 {
+        self.maximum_capacity()
+            .saturating_sub(self.free_space_in_bytes())
+    }
+
+    /// Returns the free space as a ratio of the maximum capacity (`0.0..=1.0`), storages with a
+    /// maximum capacity of `0` have a ratio of `0.0`.
+    pub fn free_space_ratio(&self) -> f64 {
+        let max = self.maximum_capacity();
+
+        if max == 0 {
+            0.0
+        } else {
+            (self.free_space_in_bytes() as f64 / max as f64).min(1.0)
+        }
+    }
+
+    /// Whether the free space is under `FULL_STORAGE_THRESHOLD` bytes.
+    pub fn is_full(&self) -> bool {
+        self.free_space_in_bytes() < FULL_STORAGE_THRESHOLD
+    }
+
     /// Returns the storage description
-    pub fn description(&self) -> Option<&str> {
+    pub fn description(&self) -> Option<Cow<'_, str>> {
         unsafe {
             if (*self.inner).StorageDescription.is_null() {
                 None
             } else {
                 let cstr = CStr::from_ptr((*self.inner).StorageDescription);
-                Some(cstr.to_str().expect("Invalid UTF-8"))
+                Some(cstr.to_string_lossy())
             }
         }
     }
 
     /// Returns the volume identifier
-    pub fn volume_identifier(&self) -> Option<&str> {
+    pub fn volume_identifier(&self) -> Option<Cow<'_, str>> {
         unsafe {
             if (*self.inner).VolumeIdentifier.is_null() {
                 None
             } else {
                 let cstr = CStr::from_ptr((*self.inner).VolumeIdentifier);
-                Some(cstr.to_str().expect("Invalid UTF-8"))
+                Some(cstr.to_string_lossy())
             }
         }
     }
 
+    /// Returns an owned copy of the metadata of this storage, see `StorageSnapshot`.
+    pub fn snapshot(&self) -> StorageSnapshot {
+        StorageSnapshot {
+            id: self.id(),
+            storage_type: self.storage_type(),
+            filesystem_type: self.filesystem_type(),
+            access_capability: self.access_capability(),
+            maximum_capacity: self.maximum_capacity(),
+            free_space_in_bytes: self.free_space_in_bytes(),
+            free_space_in_objects: self.free_space_in_objects(),
+            description: self.description().map(Cow::into_owned),
+            volume_identifier: self.volume_identifier().map(Cow::into_owned),
+        }
+    }
+
     /// Formats this storage (if its device supports the operation).
     ///
     /// **WARNING:** This **WILL DELETE ALL DATA** from the device, make sure
@@ -207,19 +505,193 @@ impl<'a> Storage<'a> {
         files_and_folders(self.owner, storage_id, parent)
     }
 
+    /// Retrieves the albums in this storage, note that this request will always perform I/O
+    /// with the device.
+    pub fn album_list(&self) -> Vec<Album<'a>> {
+        albums::album_list(self.owner, self.id())
+    }
+
+    /// Creates a new album in this storage with the given metadata and tracks, the album is
+    /// placed under `parent`, use `Parent::Root` to let the device choose its default folder.
+    pub fn create_album(
+        &self,
+        metadata: AlbumMetadata<'_>,
+        tracks: &[u32],
+        parent: Parent,
+    ) -> Result<Album<'a>> {
+        albums::create_album(self.owner, self.id(), parent, metadata, tracks)
+    }
+
+    /// Creates a new playlist in this storage with the given name and tracks, the playlist is placed
+    /// under `parent`, use `Parent::Root` to let the device choose its default folder.
+    pub fn create_playlist(
+        &self,
+        name: &str,
+        tracks: &[u32],
+        parent: Parent,
+    ) -> Result<Playlist<'a>> {
+        playlists::create_playlist(self.owner, self.id(), parent, name, tracks)
+    }
+
+    /// Retrieves the tracks (files with audio metadata) in this storage, note that this request
+    /// will always perform I/O with the device and may take a while on big collections.
+    pub fn track_listing(&self) -> Vec<Track<'a>> {
+        tracks::track_listing(self.owner, self.id())
+    }
+
+    /// Same as [`track_listing`](struct.Storage.html#method.track_listing), but calls the
+    /// given callback to report progress, return `CallbackReturn::Cancel` (or `false`) to stop
+    /// the listing earlier.
+    pub fn track_listing_with_callback<C, R>(&self, callback: C) -> Vec<Track<'a>>
+    where
+        C: FnMut(u64, u64) -> R,
+        R: Into<CallbackReturn>,
+    {
+        tracks::track_listing_with_callback(self.owner, self.id(), callback)
+    }
+
+    /// Counts (recursively) the files in this storage, folders aren't counted. If `max` is given
+    /// the count stops once it's reached, useful to show something like "1000+ files" without
+    /// scanning huge storages.
+    ///
+    /// Note that this performs a request per folder, so it may take a significant amount of
+    /// time, consider running it in a background thread.
+    pub fn count_all_files(&self, max: Option<usize>) -> usize {
+        count_all_files(self.owner, self.id(), max)
+    }
+
+    /// Returns an iterator over every file (folders included) under `root` in this storage,
+    /// depth first, see `FileWalker`.
+    ///
+    /// Note that this performs a request per folder as the iteration goes.
+    pub fn walk(&self, root: Parent) -> FileWalker<'a> {
+        FileWalker::new(self.owner, self.id(), root)
+    }
+
+    /// Resolves a path in this storage (components separated by `/`, starting from the root,
+    /// like `"/Music/Album/song.mp3"`) to the file or folder it points to, names are compared
+    /// ignoring case if `case_insensitive` is `true`. Repeated and trailing slashes are
+    /// ignored, and if many objects in a folder have the same name the first one is picked.
+    ///
+    /// Returns `Ok(None)` if some component doesn't exist, if a component other than the
+    /// last one isn't a folder, or if the path is empty (the root isn't an object). Note that
+    /// this performs a request per component.
+    pub fn resolve_path(&self, path: &str, case_insensitive: bool) -> Result<Option<File<'a>>> {
+        resolve_path(self.owner, self.id(), path, case_insensitive)
+    }
+
+    /// Same as [`files_and_folders`](struct.Storage.html#method.files_and_folders), but if
+    /// `parent` is a folder, first checks that it exists in this storage, returning
+    /// `Error::InvalidParent` otherwise. This way a stale folder id isn't mistaken for an empty
+    /// folder, note that the check costs an extra folder list request.
+    pub fn files_and_folders_checked(&self, parent: Parent) -> Result<Vec<File<'a>>> {
+        if let Parent::Folder(id) = parent {
+            let exists = self
+                .folder_list()
+                .is_some_and(|root| root.find(id).is_some());
+
+            if !exists {
+                return Err(Error::InvalidParent { id });
+            }
+        }
+
+        Ok(self.files_and_folders(parent))
+    }
+
     /// Optionally returns a `Folder`, with this struct you can build a tree
     /// structure (see `Folder` for more info)
     pub fn folder_list(&self) -> Option<Folder<'a>> {
         unsafe { get_folder_list_storage(self.owner, (*self.inner).id) }
     }
 
+    /// Retrieves the folders of this storage as an owned tree, the returned node is the root of
+    /// the storage (id `0` and empty name) and its children are the top level folders. Returns
+    /// `None` if the storage doesn't have folders (or they couldn't be retrieved).
+    pub fn folder_tree(&self) -> Option<FolderNode> {
+        folder_tree(self.owner, self.id())
+    }
+
     /// Tries to create a new folder in this storage for the relevant `MtpDevice`, returns the id
     /// of the new folder and its name, note that the name may be different due to device file
     /// system restrictions.
+    ///
+    /// You may want to check the name with `folders::is_valid_folder_name` or clean it with
+    /// `folders::sanitize_folder_name` beforehand to avoid surprises.
+    ///
+    /// Returns `Error::FlatFilesystem` if `parent` is a folder and this storage doesn't support
+    /// nested folders (see [`supports_hierarchy`](struct.Storage.html#method.supports_hierarchy)).
     pub fn create_folder<'b>(&self, name: &'b str, parent: Parent) -> Result<(u32, Cow<'b, str>)> {
+        if matches!(parent, Parent::Folder(_)) && !self.supports_hierarchy() {
+            return Err(Error::FlatFilesystem {
+                storage_id: self.id(),
+            });
+        }
+
         unsafe { create_folder(self.owner, name, parent, (*self.inner).id) }
     }
 
+    /// Creates every folder of `path` (components separated by `/`, starting from the root) that
+    /// doesn't exist yet in this storage, like `mkdir -p`, and returns the last one as a `Parent`.
+    ///
+    /// Returns `Error::FlatFilesystem` if `path` has more than one component and this storage
+    /// doesn't support nested folders (see
+    /// [`supports_hierarchy`](struct.Storage.html#method.supports_hierarchy)).
+    pub fn create_folder_all(&self, path: &str) -> Result<Parent> {
+        let components: Vec<_> = path.split('/').filter(|c| !c.is_empty()).collect();
+
+        if components.len() > 1 && !self.supports_hierarchy() {
+            return Err(Error::FlatFilesystem {
+                storage_id: self.id(),
+            });
+        }
+
+        let mut parent = Parent::Root;
+        for component in components {
+            let existing = self
+                .files_and_folders(parent)
+                .into_iter()
+                .find(|file| matches!(file.ftype(), Filetype::Folder) && file.name() == component);
+
+            parent = match existing {
+                Some(folder) => Parent::Folder(folder.id()),
+                None => Parent::Folder(self.create_folder(component, parent)?.0),
+            };
+        }
+
+        Ok(parent)
+    }
+
+    /// Tests whether this storage is actually writable, beyond what `access_capability` says
+    /// (e.g. the device may be locked or the storage full). Returns `Ok(true)` if a probe folder
+    /// could be created at the root of this storage, `Ok(false)` if the device refused it because
+    /// the storage is write-protected or full, and any other error otherwise.
+    ///
+    /// **Note:** This has a side effect, a temporary folder is created and immediately deleted
+    /// (retrying once). If it still can't be deleted `Error::ProbeNotDeleted` is returned with
+    /// the id of the folder left behind.
+    pub fn probe_writable(&self) -> Result<bool> {
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.subsec_nanos())
+            .unwrap_or_default();
+        let name = format!(".libmtp-rs-probe-{}-{}", std::process::id(), nanos);
+
+        let id = match self.create_folder(&name, Parent::Root) {
+            Ok((id, _)) => id,
+            Err(err) if is_write_refusal(&err) => return Ok(false),
+            Err(err) => return Err(err),
+        };
+
+        let probe = self.owner.dummy_object(id);
+        match probe.delete().or_else(|_| probe.delete()) {
+            Ok(()) => Ok(true),
+            Err(err) => Err(Error::ProbeNotDeleted {
+                id,
+                source: Box::new(err),
+            }),
+        }
+    }
+
     /// Retrieves a file from the device storage to a local file identified by a filename. Note
     /// that `get_file_to_path` on `Storage` and `StoragePool` are semantically the same because
     /// objects have unique ids across all the device.
@@ -227,25 +699,48 @@ impl<'a> Storage<'a> {
         files::get_file_to_path(self.owner, file, path)
     }
 
+    /// Same as `get_file_to_path`, but first creates (recursively) the missing parent
+    /// directories of `path`, failures creating them are returned as `Error::Io`.
+    pub fn get_file_to_path_create_dirs(
+        &self,
+        file: impl AsObjectId,
+        path: impl AsRef<Path>,
+    ) -> Result<()> {
+        files::get_file_to_path_create_dirs(self.owner, file, path)
+    }
+
     /// Retrieves a file from the device storage to a local file identified by a filename. Note
     /// that `get_file_to_path` on `Storage` and `StoragePool` are semantically the same because
     /// objects have unique ids across all the device.
     ///
     /// The `callback` parameter is a progress function with the following signature `(sent_bytes:
     /// u64, total_bytes: u64) -> CallbackReturn`, this way you can check the progress and if you
-    /// want to cancel operation you just return `CallbackReturn::Cancel`.
-    pub fn get_file_to_path_with_callback<C>(
+    /// want to cancel operation you just return `CallbackReturn::Cancel` (or `false`).
+    pub fn get_file_to_path_with_callback<C, R>(
         &self,
         file: impl AsObjectId,
         path: impl AsRef<Path>,
         callback: C,
     ) -> Result<()>
     where
-        C: FnMut(u64, u64) -> CallbackReturn,
+        C: FnMut(u64, u64) -> R,
+        R: Into<CallbackReturn>,
     {
         files::get_file_to_path_with_callback(self.owner, file, path, callback)
     }
 
+    /// Same as `get_file_to_path`, but keeps the average throughput of the transfer under
+    /// `max_bytes_per_sec` (`0` meaning no limit), this is useful for background transfers that
+    /// shouldn't starve other requests to the device.
+    pub fn get_file_to_path_throttled(
+        &self,
+        file: impl AsObjectId,
+        path: impl AsRef<Path>,
+        max_bytes_per_sec: u64,
+    ) -> Result<()> {
+        files::get_file_to_path_throttled(self.owner, file, path, max_bytes_per_sec)
+    }
+
     /// Retrieves a file from the device storage to a local file identified by a descriptor. Note
     /// that `get_file_to_descriptor` on `Storage` and `StoragePool` are semantically the same because
     /// objects have unique ids across all the device.
@@ -264,16 +759,17 @@ impl<'a> Storage<'a> {
     ///
     /// The `callback` parameter is a progress function with the following signature `(sent_bytes:
     /// u64, total_bytes: u64) -> CallbackReturn`, this way you can check the progress and if you
-    /// want to cancel operation you just return `CallbackReturn::Cancel`.
+    /// want to cancel operation you just return `CallbackReturn::Cancel` (or `false`).
     #[cfg(unix)]
-    pub fn get_file_to_descriptor_with_callback<C>(
+    pub fn get_file_to_descriptor_with_callback<C, R>(
         &self,
         file: impl AsObjectId,
         descriptor: impl AsRawFd,
         callback: C,
     ) -> Result<()>
     where
-        C: FnMut(u64, u64) -> CallbackReturn,
+        C: FnMut(u64, u64) -> R,
+        R: Into<CallbackReturn>,
     {
         files::get_file_to_descriptor_with_callback(self.owner, file, descriptor, callback)
     }
@@ -289,7 +785,7 @@ impl<'a> Storage<'a> {
     where
         H: FnMut(&[u8]) -> HandlerReturn,
     {
-        files::get_file_to_handler(self.owner, file, handler)
+        files::get_file_to_handler(self.owner, file, handler).map(|_| ())
     }
 
     /// Retrieves a file from the device storage and calls handler with chunks of data. Note
@@ -302,8 +798,8 @@ impl<'a> Storage<'a> {
     ///
     /// The `callback` parameter is a progress function with the following signature `(sent_bytes:
     /// u64, total_bytes: u64) -> CallbackReturn`, this way you can check the progress and if you
-    /// want to cancel operation you just return `CallbackReturn::Cancel`.
-    pub fn get_file_to_handler_with_callback<H, C>(
+    /// want to cancel operation you just return `CallbackReturn::Cancel` (or `false`).
+    pub fn get_file_to_handler_with_callback<H, C, R>(
         &self,
         file: impl AsObjectId,
         handler: H,
@@ -311,7 +807,8 @@ impl<'a> Storage<'a> {
     ) -> Result<()>
     where
         H: FnMut(&[u8]) -> HandlerReturn,
-        C: FnMut(u64, u64) -> CallbackReturn,
+        C: FnMut(u64, u64) -> R,
+        R: Into<CallbackReturn>,
     {
         files::get_file_to_handler_with_callback(self.owner, file, handler, callback)
     }
@@ -326,16 +823,45 @@ impl<'a> Storage<'a> {
     where
         C: FnMut(u64, u64) -> CallbackReturn,
     {
+        self.check_writable()?;
         let storage_id = self.id();
         files::send_file_from_path(self.owner, storage_id, path, parent, metadata)
     }
 
+    /// Same as `send_file_from_path`, but if the device doesn't support the filetype of
+    /// `metadata` it's replaced by a generic one (`UndefAudio`, `UndefVideo` or `Unknown`) that
+    /// the device supports, this way quirky devices that only accept generic types still get
+    /// the file. Returns the filetype actually used together with the new file.
+    pub fn send_file_from_path_with_fallback(
+        &self,
+        path: impl AsRef<Path>,
+        parent: Parent,
+        metadata: FileMetadata<'_>,
+    ) -> Result<(File<'a>, Filetype)> {
+        self.check_writable()?;
+        files::send_file_from_path_with_fallback(self.owner, self.id(), path, parent, metadata)
+    }
+
+    /// Same as `send_file_from_path` but without validating `metadata` (see
+    /// `FileMetadata::validate`), use it only if the device accepts metadata that would be
+    /// rejected otherwise.
+    pub fn send_file_from_path_unchecked(
+        &self,
+        path: impl AsRef<Path>,
+        parent: Parent,
+        metadata: FileMetadata<'_>,
+    ) -> Result<File<'a>> {
+        self.check_writable()?;
+        let storage_id = self.id();
+        files::send_file_from_path_unchecked(self.owner, storage_id, path, parent, metadata)
+    }
+
     /// Sends a local file to the MTP device who this storage belongs to.
     ///
     /// The `callback` parameter is a progress function with the following signature `(sent_bytes:
     /// u64, total_bytes: u64) -> CallbackReturn`, this way you can check the progress and if you
-    /// want to cancel operation you just return `CallbackReturn::Cancel`.
-    pub fn send_file_from_path_with_callback<C>(
+    /// want to cancel operation you just return `CallbackReturn::Cancel` (or `false`).
+    pub fn send_file_from_path_with_callback<C, R>(
         &self,
         path: impl AsRef<Path>,
         parent: Parent,
@@ -343,14 +869,37 @@ impl<'a> Storage<'a> {
         callback: C,
     ) -> Result<File<'a>>
     where
-        C: FnMut(u64, u64) -> CallbackReturn,
+        C: FnMut(u64, u64) -> R,
+        R: Into<CallbackReturn>,
     {
+        self.check_writable()?;
         let storage_id = self.id();
         files::send_file_from_path_with_callback(
             self.owner, storage_id, path, parent, metadata, callback,
         )
     }
 
+    /// Same as `send_file_from_path`, but keeps the average throughput of the transfer under
+    /// `max_bytes_per_sec` (`0` meaning no limit), this is useful for background transfers that
+    /// shouldn't starve other requests to the device.
+    pub fn send_file_from_path_throttled(
+        &self,
+        path: impl AsRef<Path>,
+        parent: Parent,
+        metadata: FileMetadata<'_>,
+        max_bytes_per_sec: u64,
+    ) -> Result<File<'a>> {
+        self.check_writable()?;
+        files::send_file_from_path_throttled(
+            self.owner,
+            self.id(),
+            path,
+            parent,
+            metadata,
+            max_bytes_per_sec,
+        )
+    }
+
     /// Sends a local file via descriptor to the MTP device who this storage belongs to.
     #[cfg(unix)]
     pub fn send_file_from_descriptor(
@@ -359,6 +908,7 @@ impl<'a> Storage<'a> {
         parent: Parent,
         metadata: FileMetadata<'_>,
     ) -> Result<File<'a>> {
+        self.check_writable()?;
         let storage_id = self.id();
         files::send_file_from_descriptor(self.owner, storage_id, descriptor, parent, metadata)
     }
@@ -367,9 +917,9 @@ impl<'a> Storage<'a> {
     ///
     /// The `callback` parameter is a progress function with the following signature `(sent_bytes:
     /// u64, total_bytes: u64) -> CallbackReturn`, this way you can check the progress and if you
-    /// want to cancel operation you just return `CallbackReturn::Cancel`.
+    /// want to cancel operation you just return `CallbackReturn::Cancel` (or `false`).
     #[cfg(unix)]
-    pub fn send_file_from_descriptor_with_callback<C>(
+    pub fn send_file_from_descriptor_with_callback<C, R>(
         &self,
         descriptor: impl AsRawFd,
         parent: Parent,
@@ -377,8 +927,10 @@ impl<'a> Storage<'a> {
         callback: C,
     ) -> Result<File<'a>>
     where
-        C: FnMut(u64, u64) -> CallbackReturn,
+        C: FnMut(u64, u64) -> R,
+        R: Into<CallbackReturn>,
     {
+        self.check_writable()?;
         let storage_id = self.id();
         files::send_file_from_descriptor_with_callback(
             self.owner, storage_id, descriptor, parent, metadata, callback,
@@ -400,10 +952,52 @@ impl<'a> Storage<'a> {
     where
         H: FnMut(&mut [u8]) -> HandlerReturn,
     {
+        self.check_writable()?;
         let storage_id = self.id();
         files::send_file_from_handler(self.owner, storage_id, parent, metadata, handler)
     }
 
+    /// Same as `send_file_from_handler`, but also returns the total amount of bytes given by
+    /// `handler`. If `strict` is `true` and that amount doesn't match `metadata.file_size`,
+    /// returns `Error::SizeMismatch` instead (the object is deleted if the device was set to
+    /// remove failed sends, see `MtpDevice::set_remove_failed_sends`), a mismatch usually means
+    /// a bug in the handler.
+    pub fn send_file_from_handler_counted<H>(
+        &self,
+        handler: H,
+        parent: Parent,
+        metadata: FileMetadata<'_>,
+        strict: bool,
+    ) -> Result<(File<'a>, u64)>
+    where
+        H: FnMut(&mut [u8]) -> HandlerReturn,
+    {
+        self.check_writable()?;
+        files::send_file_from_handler_counted(
+            self.owner,
+            self.id(),
+            parent,
+            metadata,
+            handler,
+            strict,
+        )
+    }
+
+    /// Sends a local file to the MTP device who this storage belongs to, reading it in chunks of
+    /// at most `buf_size` bytes, so the memory used doesn't depend on the size of the file. This
+    /// is useful to send huge files (e.g. videos) from memory-constrained hosts.
+    pub fn send_large_file(
+        &self,
+        path: impl AsRef<Path>,
+        parent: Parent,
+        metadata: FileMetadata<'_>,
+        buf_size: usize,
+    ) -> Result<File<'a>> {
+        self.check_writable()?;
+        let storage_id = self.id();
+        files::send_large_file(self.owner, storage_id, path, parent, metadata, buf_size)
+    }
+
     /// Sends a bunch of data to the MTP device who this storage belongs to.
     ///
     /// The `handler` parameter is a function that gives you a chunk to write data with the
@@ -413,8 +1007,8 @@ impl<'a> Storage<'a> {
     ///
     /// The `callback` parameter is a progress function with the following signature `(sent_bytes:
     /// u64, total_bytes: u64) -> CallbackReturn`, this way you can check the progress and if you
-    /// want to cancel operation you just return `CallbackReturn::Cancel`.
-    pub fn send_file_from_handler_with_callback<H, C>(
+    /// want to cancel operation you just return `CallbackReturn::Cancel` (or `false`).
+    pub fn send_file_from_handler_with_callback<H, C, R>(
         &self,
         handler: H,
         parent: Parent,
@@ -423,13 +1017,133 @@ impl<'a> Storage<'a> {
     ) -> Result<File<'a>>
     where
         H: FnMut(&mut [u8]) -> HandlerReturn,
-        C: FnMut(u64, u64) -> CallbackReturn,
+        C: FnMut(u64, u64) -> R,
+        R: Into<CallbackReturn>,
     {
+        self.check_writable()?;
         let storage_id = self.id();
         files::send_file_from_handler_with_callback(
             self.owner, storage_id, parent, metadata, handler, callback,
         )
     }
+
+    /// Sends a local track to the MTP device who this storage belongs to, besides the file
+    /// metadata it stores the audio metadata (artist, album, genre, etc) so the music player of
+    /// the device shows proper tags. Returns the created `Track`.
+    pub fn send_track_from_path(
+        &self,
+        path: impl AsRef<Path>,
+        parent: Parent,
+        metadata: TrackMetadata<'_>,
+    ) -> Result<Track<'a>> {
+        self.check_writable()?;
+        tracks::send_track_from_path(self.owner, self.id(), path, parent, metadata)
+    }
+
+    /// Same as [`send_track_from_path`](struct.Storage.html#method.send_track_from_path), but
+    /// calls the given callback to report progress, return `CallbackReturn::Cancel` (or `false`)
+    /// to cancel the operation.
+    pub fn send_track_from_path_with_callback<C, R>(
+        &self,
+        path: impl AsRef<Path>,
+        parent: Parent,
+        metadata: TrackMetadata<'_>,
+        callback: C,
+    ) -> Result<Track<'a>>
+    where
+        C: FnMut(u64, u64) -> R,
+        R: Into<CallbackReturn>,
+    {
+        self.check_writable()?;
+        tracks::send_track_from_path_with_callback(
+            self.owner,
+            self.id(),
+            path,
+            parent,
+            metadata,
+            callback,
+        )
+    }
+
+    /// Sends a track from a local file descriptor, see
+    /// [`send_track_from_path`](struct.Storage.html#method.send_track_from_path).
+    #[cfg(unix)]
+    pub fn send_track_from_descriptor(
+        &self,
+        descriptor: impl AsRawFd,
+        parent: Parent,
+        metadata: TrackMetadata<'_>,
+    ) -> Result<Track<'a>> {
+        self.check_writable()?;
+        tracks::send_track_from_descriptor(self.owner, self.id(), descriptor, parent, metadata)
+    }
+
+    /// Same as
+    /// [`send_track_from_descriptor`](struct.Storage.html#method.send_track_from_descriptor), but
+    /// calls the given callback to report progress.
+    #[cfg(unix)]
+    pub fn send_track_from_descriptor_with_callback<C, R>(
+        &self,
+        descriptor: impl AsRawFd,
+        parent: Parent,
+        metadata: TrackMetadata<'_>,
+        callback: C,
+    ) -> Result<Track<'a>>
+    where
+        C: FnMut(u64, u64) -> R,
+        R: Into<CallbackReturn>,
+    {
+        self.check_writable()?;
+        tracks::send_track_from_descriptor_with_callback(
+            self.owner,
+            self.id(),
+            descriptor,
+            parent,
+            metadata,
+            callback,
+        )
+    }
+
+    /// Sends a track whose data is given by `handler`, see
+    /// [`send_file_from_handler`](struct.Storage.html#method.send_file_from_handler) and
+    /// [`send_track_from_path`](struct.Storage.html#method.send_track_from_path).
+    pub fn send_track_from_handler<H>(
+        &self,
+        handler: H,
+        parent: Parent,
+        metadata: TrackMetadata<'_>,
+    ) -> Result<Track<'a>>
+    where
+        H: FnMut(&mut [u8]) -> HandlerReturn,
+    {
+        self.check_writable()?;
+        tracks::send_track_from_handler(self.owner, self.id(), parent, metadata, handler)
+    }
+
+    /// Same as [`send_track_from_handler`](struct.Storage.html#method.send_track_from_handler),
+    /// but calls the given callback to report progress.
+    pub fn send_track_from_handler_with_callback<H, C, R>(
+        &self,
+        handler: H,
+        parent: Parent,
+        metadata: TrackMetadata<'_>,
+        callback: C,
+    ) -> Result<Track<'a>>
+    where
+        H: FnMut(&mut [u8]) -> HandlerReturn,
+        C: FnMut(u64, u64) -> R,
+        R: Into<CallbackReturn>,
+    {
+        self.check_writable()?;
+        tracks::send_track_from_handler_with_callback(
+            self.owner,
+            self.id(),
+            parent,
+            metadata,
+            handler,
+            callback,
+        )
+    }
 }
 
 /// Represents all the storage "pool" of one MTP device, contain all the storage entries
@@ -442,6 +1156,10 @@ pub struct StoragePool<'a> {
 
     #[derivative(Debug = "ignore")]
     owner: &'a MtpDevice,
+
+    /// Folder tree of all the storages, `None` until it's needed.
+    #[derivative(Debug = "ignore")]
+    folders: RefCell<Option<FolderTree>>,
 }
 
 /// Iterator that allows us to get each `Storage` with its id.
@@ -455,7 +1173,7 @@ impl<'a> Iterator for StoragePoolIter<'a> {
     type Item = (u32, &'a Storage<'a>);
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.itr >= self.pool.len() {
+        if self.itr >= self.order.len() {
             None
         } else {
             let next_id = self.order[self.itr];
@@ -485,7 +1203,12 @@ impl<'a> StoragePool<'a> {
                 ptr = (*ptr).next;
             }
 
-            Self { order, pool, owner }
+            Self {
+                order,
+                pool,
+                owner,
+                folders: RefCell::new(None),
+            }
         }
     }
 
@@ -499,6 +1222,97 @@ impl<'a> StoragePool<'a> {
         self.pool.get(&id)
     }
 
+    /// Finds the storage described by a snapshot taken before refreshing the storage (since the
+    /// ids of the storages may change across `MtpDevice::update_storage`). A storage matches if
+    /// it has the same description, volume identifier and storage type of the snapshot, if many
+    /// storages match, the one with the same id is preferred.
+    ///
+    /// If the snapshot doesn't have a description nor a volume identifier, only a storage with
+    /// the same id (and type) matches. Note that this heuristic may fail if a device has many
+    /// identical storages (e.g. two cards without volume identifier) or renames them.
+    pub fn reacquire(&self, previous: &StorageSnapshot) -> Option<&Storage<'a>> {
+        let same_identity = |storage: &&Storage<'a>| {
+            storage.storage_type() == previous.storage_type
+                && storage.description().as_deref() == previous.description.as_deref()
+                && storage.volume_identifier().as_deref() == previous.volume_identifier.as_deref()
+        };
+
+        if let Some(storage) = self.by_id(previous.id).filter(same_identity) {
+            return Some(storage);
+        }
+
+        if previous.description.is_none() && previous.volume_identifier.is_none() {
+            return None;
+        }
+
+        self.order
+            .iter()
+            .filter_map(|id| self.pool.get(id))
+            .find(same_identity)
+    }
+
+    /// Returns the writable storage (`AccessCapability::ReadWrite`) with the most free space in
+    /// bytes, if there's one.
+    pub fn storage_with_most_free_space(&self) -> Option<&Storage<'a>> {
+        self.order
+            .iter()
+            .filter_map(|id| self.pool.get(id))
+            .filter(|storage| storage.access_capability() == AccessCapability::ReadWrite)
+            .max_by_key(|storage| storage.free_space_in_bytes())
+    }
+
+    /// Sends a local file to the root folder of the storage picked by
+    /// [`storage_with_most_free_space`](#method.storage_with_most_free_space), if there isn't
+    /// a writable storage it's sent to the primary storage (id `0`) and the device decides.
+    pub fn send_file_to_best_storage(
+        &self,
+        path: impl AsRef<Path>,
+        metadata: FileMetadata<'_>,
+    ) -> Result<File<'a>> {
+        let storage_id = self
+            .storage_with_most_free_space()
+            .map_or(0, |storage| storage.id());
+
+        files::send_file_from_path(self.owner, storage_id, path, Parent::Root, metadata)
+    }
+
+    /// Returns the sum of the free space in bytes of every storage in this pool, `0` if it's
+    /// empty.
+    pub fn total_free_space_in_bytes(&self) -> u64 {
+        self.pool
+            .values()
+            .map(Storage::free_space_in_bytes)
+            .fold(0, u64::saturating_add)
+    }
+
+    /// Returns the sum of the maximum capacity of every storage in this pool, `0` if it's empty.
+    pub fn total_capacity(&self) -> u64 {
+        self.pool
+            .values()
+            .map(Storage::maximum_capacity)
+            .fold(0, u64::saturating_add)
+    }
+
+    /// Returns the sum of the free space in objects of every storage in this pool, `0` if it's
+    /// empty. Saturates at `u64::MAX`, since some devices report it as unknown this way.
+    pub fn total_free_space_in_objects(&self) -> u64 {
+        self.pool
+            .values()
+            .map(Storage::free_space_in_objects)
+            .fold(0, u64::saturating_add)
+    }
+
+    /// Returns the storages (in the same order as `iter`) whose maximum capacity isn't zero,
+    /// some devices report phantom storages like empty card slots, use `iter` if you care
+    /// about them.
+    pub fn non_empty_storages(&self) -> Vec<&Storage<'a>> {
+        self.order
+            .iter()
+            .filter_map(|id| self.pool.get(id))
+            .filter(|storage| storage.maximum_capacity() > 0)
+            .collect()
+    }
+
     /// Returns an iterator over the storages, this is a HashMap iterator.
     pub fn iter(&'a self) -> StoragePoolIter<'a> {
         StoragePoolIter {
@@ -508,6 +1322,12 @@ impl<'a> StoragePool<'a> {
         }
     }
 
+    /// Returns an owned copy of the metadata of every storage in this pool (in the same order as
+    /// `iter`), see `StorageSnapshot`.
+    pub fn snapshot(&self) -> Vec<StorageSnapshot> {
+        self.iter().map(|(_, storage)| storage.snapshot()).collect()
+    }
+
     /// Retrieves the contents of a certain folder (`parent`) in all storages, the result contains
     /// both files and folders, note that this request will always perform I/O with the device.
     pub fn files_and_folders(&self, parent: Parent) -> Vec<File<'a>> {
@@ -520,11 +1340,142 @@ impl<'a> StoragePool<'a> {
         get_folder_list(self.owner)
     }
 
+    /// Counts (recursively) the files in all storages, folders aren't counted. If `max` is given
+    /// the count stops once it's reached, useful to show something like "1000+ files" without
+    /// scanning huge devices.
+    ///
+    /// Note that this performs a request per folder, so it may take a significant amount of
+    /// time, consider running it in a background thread.
+    pub fn count_all_files(&self, max: Option<usize>) -> usize {
+        count_all_files(self.owner, 0, max)
+    }
+
+    /// Returns an iterator over every file (folders included) under `root` in all storages,
+    /// depth first, see `FileWalker`.
+    ///
+    /// Note that this performs a request per folder as the iteration goes.
+    pub fn walk(&self, root: Parent) -> FileWalker<'a> {
+        FileWalker::new(self.owner, 0, root)
+    }
+
+    /// Resolves a folder path (components separated by `/`, starting from the root) to its
+    /// `Parent`, an empty path resolves to `Parent::Root`. If there are folders with the same
+    /// path in many storages, the first one found is returned.
+    ///
+    /// The folder tree is fetched once and cached, so repeated lookups don't perform any I/O,
+    /// call [`refresh_folders`](struct.StoragePool.html#method.refresh_folders) after creating,
+    /// moving, renaming or deleting folders by other means than this pool.
+    pub fn folder_by_path(&self, path: &str) -> Option<Parent> {
+        let mut folders = self.folders.borrow_mut();
+        let tree = folders.get_or_insert_with(|| FolderTree::fetch(self.owner));
+
+        tree.find_by_path(path)
+    }
+
+    /// Invalidates the cached folder tree used by
+    /// [`folder_by_path`](struct.StoragePool.html#method.folder_by_path), the next lookup will
+    /// fetch it again.
+    pub fn refresh_folders(&self) {
+        self.folders.replace(None);
+    }
+
+    /// Deletes the given object (see `Object::delete`) and removes it from the cached folder tree
+    /// used by [`folder_by_path`](struct.StoragePool.html#method.folder_by_path), so deleted
+    /// folders don't keep resolving without a full refresh.
+    pub fn delete_and_uncache(&self, object: impl Object) -> Result<()> {
+        let id = object.id();
+        object.delete()?;
+
+        if let Some(tree) = self.folders.borrow_mut().as_mut() {
+            tree.remove(id);
+        }
+
+        Ok(())
+    }
+
     /// Tries to create a new folder in the default storage of the relevant `MtpDevice`, returns
     /// the id of the new folder and its name, note that the name may be different due to device
     /// file system restrictions.
+    ///
+    /// You may want to check the name with `folders::is_valid_folder_name` or clean it with
+    /// `folders::sanitize_folder_name` beforehand to avoid surprises.
     pub fn create_folder<'b>(&self, name: &'b str, parent: Parent) -> Result<(u32, Cow<'b, str>)> {
-        create_folder(self.owner, name, parent, 0)
+        let res = create_folder(self.owner, name, parent, 0);
+
+        if res.is_ok() {
+            self.refresh_folders();
+        }
+
+        res
+    }
+
+    /// Retrieves the albums in all the storages, note that this request will always perform I/O
+    /// with the device.
+    pub fn album_list(&self) -> Vec<Album<'a>> {
+        albums::album_list(self.owner, 0)
+    }
+
+    /// Creates a new album in the primary storage with the given metadata and tracks, the album
+    /// is placed under `parent`, use `Parent::Root` to let the device choose its default folder.
+    pub fn create_album(
+        &self,
+        metadata: AlbumMetadata<'_>,
+        tracks: &[u32],
+        parent: Parent,
+    ) -> Result<Album<'a>> {
+        albums::create_album(self.owner, 0, parent, metadata, tracks)
+    }
+
+    /// Retrieves the playlists in all the storages, note that this request will always perform
+    /// I/O with the device.
+    pub fn playlist_list(&self) -> Vec<Playlist<'a>> {
+        playlists::playlist_list(self.owner)
+    }
+
+    /// Creates a new playlist in the primary storage with the given name and tracks, the playlist is placed
+    /// under `parent`, use `Parent::Root` to let the device choose its default folder.
+    pub fn create_playlist(
+        &self,
+        name: &str,
+        tracks: &[u32],
+        parent: Parent,
+    ) -> Result<Playlist<'a>> {
+        playlists::create_playlist(self.owner, 0, parent, name, tracks)
+    }
+
+    /// Retrieves the tracks (files with audio metadata) in all the storages, note that this
+    /// request will always perform I/O with the device and may take a while on big collections.
+    pub fn track_listing(&self) -> Vec<Track<'a>> {
+        tracks::track_listing(self.owner, 0)
+    }
+
+    /// Same as [`track_listing`](struct.StoragePool.html#method.track_listing), but calls the
+    /// given callback to report progress, return `CallbackReturn::Cancel` (or `false`) to stop
+    /// the listing earlier.
+    pub fn track_listing_with_callback<C, R>(&self, callback: C) -> Vec<Track<'a>>
+    where
+        C: FnMut(u64, u64) -> R,
+        R: Into<CallbackReturn>,
+    {
+        tracks::track_listing_with_callback(self.owner, 0, callback)
+    }
+
+    /// Retrieves the metadata of the track with the given id, see
+    /// [`Track::from_id`](tracks/struct.Track.html#method.from_id).
+    pub fn get_track(&self, track: impl AsObjectId) -> Result<Track<'a>> {
+        Track::from_id(self.owner, track)
+    }
+
+    /// Retrieves a single album given its id, note that albums have unique ids across all the
+    /// device.
+    pub fn get_album(&self, album: impl AsObjectId) -> Result<Album<'a>> {
+        albums::get_album(self.owner, album)
+    }
+
+    /// Retrieves a single playlist given its id, note that playlists have unique ids across all
+    /// the device.
+    pub fn get_playlist(&self, playlist: impl AsObjectId) -> Result<Playlist<'a>> {
+        playlists::get_playlist(self.owner, playlist)
     }
 
     /// Retrieves a file from the device storage to a local file identified by a filename. Note
@@ -534,25 +1485,74 @@ impl<'a> StoragePool<'a> {
         files::get_file_to_path(self.owner, file, path)
     }
 
+    /// Same as `get_file_to_path`, but first creates (recursively) the missing parent
+    /// directories of `path`, failures creating them are returned as `Error::Io`.
+    pub fn get_file_to_path_create_dirs(
+        &self,
+        file: impl AsObjectId,
+        path: impl AsRef<Path>,
+    ) -> Result<()> {
+        files::get_file_to_path_create_dirs(self.owner, file, path)
+    }
+
+    /// Retrieves many files from the device to the local directory `dir`, each one named after
+    /// its name in the device, and returns the number of files retrieved.
+    ///
+    /// The names are sanitized with `folders::sanitize_folder_name`, so every file stays directly
+    /// under `dir`. If a name is still invalid (e.g. `..`) or two files would get the same name,
+    /// `Error::InvalidFileName` or `Error::DuplicateFileName` is returned before retrieving
+    /// anything. A file whose transfer is cancelled is removed.
+    ///
+    /// The `callback` parameter is a progress function for the whole batch with the following
+    /// signature `(file_index: usize, files_total: usize, sent_bytes: u64, total_bytes: u64) ->
+    /// CallbackReturn`, where the bytes are the sum over all the files. If it returns
+    /// `CallbackReturn::Cancel` (or `false`) the batch stops and the number of files retrieved
+    /// until then is returned, on the first failure the error is returned instead.
+    pub fn get_files_to_dir<C, R>(
+        &self,
+        files: &[impl AsObjectId],
+        dir: impl AsRef<Path>,
+        callback: C,
+    ) -> Result<usize>
+    where
+        C: FnMut(usize, usize, u64, u64) -> R,
+        R: Into<CallbackReturn>,
+    {
+        files::get_files_to_dir(self.owner, files, dir, callback)
+    }
+
     /// Retrieves a file from the device storage to a local file identified by a filename. Note
     /// that `get_file_to_path` on `Storage` and `StoragePool` are semantically the same because
     /// objects have unique ids across all the device.
     ///
     /// The `callback` parameter is a progress function with the following signature `(sent_bytes:
     /// u64, total_bytes: u64) -> CallbackReturn`, this way you can check the progress and if you
-    /// want to cancel operation you just return `CallbackReturn::Cancel`.
-    pub fn get_file_to_path_with_callback<C>(
+    /// want to cancel operation you just return `CallbackReturn::Cancel` (or `false`).
+    pub fn get_file_to_path_with_callback<C, R>(
         &self,
         file: impl AsObjectId,
         path: impl AsRef<Path>,
         callback: C,
     ) -> Result<()>
     where
-        C: FnMut(u64, u64) -> CallbackReturn,
+        C: FnMut(u64, u64) -> R,
+        R: Into<CallbackReturn>,
     {
         files::get_file_to_path_with_callback(self.owner, file, path, callback)
     }
 
+    /// Same as `get_file_to_path`, but keeps the average throughput of the transfer under
+    /// `max_bytes_per_sec` (`0` meaning no limit), this is useful for background transfers that
+    /// shouldn't starve other requests to the device.
+    pub fn get_file_to_path_throttled(
+        &self,
+        file: impl AsObjectId,
+        path: impl AsRef<Path>,
+        max_bytes_per_sec: u64,
+    ) -> Result<()> {
+        files::get_file_to_path_throttled(self.owner, file, path, max_bytes_per_sec)
+    }
+
     /// Retrieves a file from the device storage to a local file identified by a descriptor. Note
     /// that `get_file_to_descriptor` on `Storage` and `StoragePool` are semantically the same because
     /// objects have unique ids across all the device.
@@ -571,16 +1571,17 @@ impl<'a> StoragePool<'a> {
     ///
     /// The `callback` parameter is a progress function with the following signature `(sent_bytes:
     /// u64, total_bytes: u64) -> CallbackReturn`, this way you can check the progress and if you
-    /// want to cancel operation you just return `CallbackReturn::Cancel`.
+    /// want to cancel operation you just return `CallbackReturn::Cancel` (or `false`).
     #[cfg(unix)]
-    pub fn get_file_to_descriptor_with_callback<C>(
+    pub fn get_file_to_descriptor_with_callback<C, R>(
         &self,
         file: impl AsObjectId,
         descriptor: impl AsRawFd,
         callback: C,
     ) -> Result<()>
     where
-        C: FnMut(u64, u64) -> CallbackReturn,
+        C: FnMut(u64, u64) -> R,
+        R: Into<CallbackReturn>,
     {
         files::get_file_to_descriptor_with_callback(self.owner, file, descriptor, callback)
     }
@@ -596,7 +1597,7 @@ impl<'a> StoragePool<'a> {
     where
         H: FnMut(&[u8]) -> HandlerReturn,
     {
-        files::get_file_to_handler(self.owner, file, handler)
+        files::get_file_to_handler(self.owner, file, handler).map(|_| ())
     }
 
     /// Retrieves a file from the device storage and calls handler with chunks of data. Note
@@ -609,8 +1610,8 @@ impl<'a> StoragePool<'a> {
     ///
     /// The `callback` parameter is a progress function with the following signature `(sent_bytes:
     /// u64, total_bytes: u64) -> CallbackReturn`, this way you can check the progress and if you
-    /// want to cancel operation you just return `CallbackReturn::Cancel`.
-    pub fn get_file_to_handler_with_callback<H, C>(
+    /// want to cancel operation you just return `CallbackReturn::Cancel` (or `false`).
+    pub fn get_file_to_handler_with_callback<H, C, R>(
         &self,
         file: impl AsObjectId,
         handler: H,
@@ -618,7 +1619,8 @@ impl<'a> StoragePool<'a> {
     ) -> Result<()>
     where
         H: FnMut(&[u8]) -> HandlerReturn,
-        C: FnMut(u64, u64) -> CallbackReturn,
+        C: FnMut(u64, u64) -> R,
+        R: Into<CallbackReturn>,
     {
         files::get_file_to_handler_with_callback(self.owner, file, handler, callback)
     }
@@ -638,13 +1640,39 @@ impl<'a> StoragePool<'a> {
         files::send_file_from_path(self.owner, storage_id, path, parent, metadata)
     }
 
+    /// Same as `send_file_from_path`, but if the device doesn't support the filetype of
+    /// `metadata` it's replaced by a generic one (`UndefAudio`, `UndefVideo` or `Unknown`) that
+    /// the device supports, this way quirky devices that only accept generic types still get
+    /// the file. Returns the filetype actually used together with the new file.
+    pub fn send_file_from_path_with_fallback(
+        &self,
+        path: impl AsRef<Path>,
+        parent: Parent,
+        metadata: FileMetadata<'_>,
+    ) -> Result<(File<'a>, Filetype)> {
+        files::send_file_from_path_with_fallback(self.owner, 0, path, parent, metadata)
+    }
+
+    /// Same as `send_file_from_path` but without validating `metadata` (see
+    /// `FileMetadata::validate`), use it only if the device accepts metadata that would be
+    /// rejected otherwise. Note that this method will send the file to the primary storage.
+    pub fn send_file_from_path_unchecked(
+        &self,
+        path: impl AsRef<Path>,
+        parent: Parent,
+        metadata: FileMetadata<'_>,
+    ) -> Result<File<'a>> {
+        let storage_id = 0;
+        files::send_file_from_path_unchecked(self.owner, storage_id, path, parent, metadata)
+    }
+
     /// Sends a local file to the MTP device who this storage belongs to, note that this method
     /// will send the file to the primary storage.
     ///
     /// The `callback` parameter is a progress function with the following signature `(sent_bytes:
     /// u64, total_bytes: u64) -> CallbackReturn`, this way you can check the progress and if you
-    /// want to cancel operation you just return `CallbackReturn::Cancel`.
-    pub fn send_file_from_path_with_callback<C>(
+    /// want to cancel operation you just return `CallbackReturn::Cancel` (or `false`).
+    pub fn send_file_from_path_with_callback<C, R>(
         &self,
         path: impl AsRef<Path>,
         parent: Parent,
@@ -652,7 +1680,8 @@ impl<'a> StoragePool<'a> {
         callback: C,
     ) -> Result<File<'a>>
     where
-        C: FnMut(u64, u64) -> CallbackReturn,
+        C: FnMut(u64, u64) -> R,
+        R: Into<CallbackReturn>,
     {
         let storage_id = 0;
         files::send_file_from_path_with_callback(
@@ -660,6 +1689,26 @@ impl<'a> StoragePool<'a> {
         )
     }
 
+    /// Same as `send_file_from_path`, but keeps the average throughput of the transfer under
+    /// `max_bytes_per_sec` (`0` meaning no limit), this is useful for background transfers that
+    /// shouldn't starve other requests to the device.
+    pub fn send_file_from_path_throttled(
+        &self,
+        path: impl AsRef<Path>,
+        parent: Parent,
+        metadata: FileMetadata<'_>,
+        max_bytes_per_sec: u64,
+    ) -> Result<File<'a>> {
+        files::send_file_from_path_throttled(
+            self.owner,
+            0,
+            path,
+            parent,
+            metadata,
+            max_bytes_per_sec,
+        )
+    }
+
     /// Sends a local file via descriptor to the MTP device who this storage belongs to, note
     /// that this method will send the file to the primary storage.
     #[cfg(unix)]
@@ -678,9 +1727,9 @@ impl<'a> StoragePool<'a> {
     ///
     /// The `callback` parameter is a progress function with the following signature `(sent_bytes:
     /// u64, total_bytes: u64) -> CallbackReturn`, this way you can check the progress and if you
-    /// want to cancel operation you just return `CallbackReturn::Cancel`.
+    /// want to cancel operation you just return `CallbackReturn::Cancel` (or `false`).
     #[cfg(unix)]
-    pub fn send_file_from_descriptor_with_callback<C>(
+    pub fn send_file_from_descriptor_with_callback<C, R>(
         &self,
         descriptor: impl AsRawFd,
         parent: Parent,
@@ -688,7 +1737,8 @@ impl<'a> StoragePool<'a> {
         callback: C,
     ) -> Result<File<'a>>
     where
-        C: FnMut(u64, u64) -> CallbackReturn,
+        C: FnMut(u64, u64) -> R,
+        R: Into<CallbackReturn>,
     {
         let storage_id = 0;
         files::send_file_from_descriptor_with_callback(
@@ -716,6 +1766,38 @@ impl<'a> StoragePool<'a> {
         files::send_file_from_handler(self.owner, storage_id, parent, metadata, handler)
     }
 
+    /// Same as `send_file_from_handler`, but also returns the total amount of bytes given by
+    /// `handler`. If `strict` is `true` and that amount doesn't match `metadata.file_size`,
+    /// returns `Error::SizeMismatch` instead (the object is deleted if the device was set to
+    /// remove failed sends, see `MtpDevice::set_remove_failed_sends`), a mismatch usually means
+    /// a bug in the handler.
+    pub fn send_file_from_handler_counted<H>(
+        &self,
+        handler: H,
+        parent: Parent,
+        metadata: FileMetadata<'_>,
+        strict: bool,
+    ) -> Result<(File<'a>, u64)>
+    where
+        H: FnMut(&mut [u8]) -> HandlerReturn,
+    {
+        files::send_file_from_handler_counted(self.owner, 0, parent, metadata, handler, strict)
+    }
+
+    /// Sends a local file to the MTP device who this storage belongs to, reading it in chunks of
+    /// at most `buf_size` bytes, so the memory used doesn't depend on the size of the file. Note
+    /// that this method will send the file to primary storage.
+    pub fn send_large_file(
+        &self,
+        path: impl AsRef<Path>,
+        parent: Parent,
+        metadata: FileMetadata<'_>,
+        buf_size: usize,
+    ) -> Result<File<'a>> {
+        let storage_id = 0;
+        files::send_large_file(self.owner, storage_id, path, parent, metadata, buf_size)
+    }
+
     /// Sends a bunch of data to the MTP device who this storage belongs to, note that this
     /// method will send the file to primary storage.
     ///
@@ -726,8 +1808,8 @@ impl<'a> StoragePool<'a> {
     ///
     /// The `callback` parameter is a progress function with the following signature `(sent_bytes:
     /// u64, total_bytes: u64) -> CallbackReturn`, this way you can check the progress and if you
-    /// want to cancel operation you just return `CallbackReturn::Cancel`.
-    pub fn send_file_from_handler_with_callback<H, C>(
+    /// want to cancel operation you just return `CallbackReturn::Cancel` (or `false`).
+    pub fn send_file_from_handler_with_callback<H, C, R>(
         &self,
         handler: H,
         parent: Parent,
@@ -736,11 +1818,400 @@ impl<'a> StoragePool<'a> {
     ) -> Result<File<'a>>
     where
         H: FnMut(&mut [u8]) -> HandlerReturn,
-        C: FnMut(u64, u64) -> CallbackReturn,
+        C: FnMut(u64, u64) -> R,
+        R: Into<CallbackReturn>,
     {
         let storage_id = 0;
         files::send_file_from_handler_with_callback(
             self.owner, storage_id, parent, metadata, handler, callback,
         )
     }
+
+    /// Sends a local track to the MTP device who this pool belongs to, besides the file
+    /// metadata it stores the audio metadata (artist, album, genre, etc) so the music player of
+    /// the device shows proper tags. Returns the created `Track`.
+    pub fn send_track_from_path(
+        &self,
+        path: impl AsRef<Path>,
+        parent: Parent,
+        metadata: TrackMetadata<'_>,
+    ) -> Result<Track<'a>> {
+        tracks::send_track_from_path(self.owner, 0, path, parent, metadata)
+    }
+
+    /// Same as [`send_track_from_path`](struct.StoragePool.html#method.send_track_from_path), but
+    /// calls the given callback to report progress, return `CallbackReturn::Cancel` (or `false`)
+    /// to cancel the operation.
+    pub fn send_track_from_path_with_callback<C, R>(
+        &self,
+        path: impl AsRef<Path>,
+        parent: Parent,
+        metadata: TrackMetadata<'_>,
+        callback: C,
+    ) -> Result<Track<'a>>
+    where
+        C: FnMut(u64, u64) -> R,
+        R: Into<CallbackReturn>,
+    {
+        tracks::send_track_from_path_with_callback(self.owner, 0, path, parent, metadata, callback)
+    }
+
+    /// Sends a track from a local file descriptor, see
+    /// [`send_track_from_path`](struct.StoragePool.html#method.send_track_from_path).
+    #[cfg(unix)]
+    pub fn send_track_from_descriptor(
+        &self,
+        descriptor: impl AsRawFd,
+        parent: Parent,
+        metadata: TrackMetadata<'_>,
+    ) -> Result<Track<'a>> {
+        tracks::send_track_from_descriptor(self.owner, 0, descriptor, parent, metadata)
+    }
+
+    /// Same as
+    /// [`send_track_from_descriptor`](struct.StoragePool.html#method.send_track_from_descriptor), but
+    /// calls the given callback to report progress.
+    #[cfg(unix)]
+    pub fn send_track_from_descriptor_with_callback<C, R>(
+        &self,
+        descriptor: impl AsRawFd,
+        parent: Parent,
+        metadata: TrackMetadata<'_>,
+        callback: C,
+    ) -> Result<Track<'a>>
+    where
+        C: FnMut(u64, u64) -> R,
+        R: Into<CallbackReturn>,
+    {
+        tracks::send_track_from_descriptor_with_callback(
+            self.owner, 0, descriptor, parent, metadata, callback,
+        )
+    }
+
+    /// Sends a track whose data is given by `handler`, see
+    /// [`send_file_from_handler`](struct.StoragePool.html#method.send_file_from_handler) and
+    /// [`send_track_from_path`](struct.StoragePool.html#method.send_track_from_path).
+    pub fn send_track_from_handler<H>(
+        &self,
+        handler: H,
+        parent: Parent,
+        metadata: TrackMetadata<'_>,
+    ) -> Result<Track<'a>>
+    where
+        H: FnMut(&mut [u8]) -> HandlerReturn,
+    {
+        tracks::send_track_from_handler(self.owner, 0, parent, metadata, handler)
+    }
+
+    /// Same as [`send_track_from_handler`](struct.StoragePool.html#method.send_track_from_handler),
+    /// but calls the given callback to report progress.
+    pub fn send_track_from_handler_with_callback<H, C, R>(
+        &self,
+        handler: H,
+        parent: Parent,
+        metadata: TrackMetadata<'_>,
+        callback: C,
+    ) -> Result<Track<'a>>
+    where
+        H: FnMut(&mut [u8]) -> HandlerReturn,
+        C: FnMut(u64, u64) -> R,
+        R: Into<CallbackReturn>,
+    {
+        tracks::send_track_from_handler_with_callback(
+            self.owner, 0, parent, metadata, handler, callback,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::mem::ManuallyDrop;
+
+    /// Metadata of a storage built in memory by the fixtures below, the storages are tied to a
+    /// fake device, so only methods that don't make requests can be used.
+    #[derive(Clone, Copy)]
+    struct FakeStorage {
+        id: u32,
+        access: AccessCapability,
+        max: u64,
+        free: u64,
+        free_objects: u64,
+    }
+
+    impl FakeStorage {
+        fn new(id: u32, max: u64, free: u64) -> Self {
+            FakeStorage {
+                id,
+                access: AccessCapability::ReadWrite,
+                max,
+                free,
+                free_objects: 0,
+            }
+        }
+
+        fn access(self, access: AccessCapability) -> Self {
+            FakeStorage { access, ..self }
+        }
+
+        fn free_objects(self, free_objects: u64) -> Self {
+            FakeStorage {
+                free_objects,
+                ..self
+            }
+        }
+
+        fn to_raw(self) -> ffi::LIBMTP_devicestorage_t {
+            let mut raw: ffi::LIBMTP_devicestorage_t = unsafe { std::mem::zeroed() };
+            raw.id = self.id;
+            raw.AccessCapability = self.access as u16;
+            raw.MaxCapacity = self.max;
+            raw.FreeSpaceInBytes = self.free;
+            raw.FreeSpaceInObjects = self.free_objects;
+            raw
+        }
+    }
+
+    fn with_storage<T>(fake: FakeStorage, test: impl FnOnce(&Storage) -> T) -> T {
+        let device = MtpDevice::fake();
+        let mut raw = fake.to_raw();
+
+        test(&Storage {
+            inner: &mut raw,
+            owner: &device,
+        })
+    }
+
+    /// Builds a pool with the given storages linked in order, like `libmtp` does.
+    fn with_pool<T>(fakes: &[FakeStorage], test: impl FnOnce(&StoragePool) -> T) -> T {
+        let device = MtpDevice::fake();
+        let mut raws: Vec<_> = fakes.iter().map(|fake| fake.to_raw()).collect();
+
+        for i in 1..raws.len() {
+            raws[i - 1].next = &mut raws[i];
+        }
+
+        let first = raws
+            .first_mut()
+            .map_or(std::ptr::null_mut(), |raw| raw as *mut _);
+
+        // Never dropped, so the tests need no `libmtp` call to free the (empty) folder tree.
+        let pool = ManuallyDrop::new(StoragePool::from_raw(&device, first));
+        test(&pool)
+    }
+
+    #[test]
+    fn pool_iterates_in_order() {
+        let fakes = [
+            FakeStorage::new(0x0001_0001, 0, 0),
+            FakeStorage::new(0x0002_0001, 0, 0),
+            FakeStorage::new(5, 0, 0),
+        ];
+
+        with_pool(&fakes, |pool| {
+            let ids: Vec<_> = pool.iter().map(|(id, _)| id).collect();
+            assert_eq!(ids, [0x0001_0001, 0x0002_0001, 5]);
+
+            for (id, storage) in pool.iter() {
+                assert_eq!(storage.id(), id);
+            }
+
+            let mut iter = pool.iter();
+            assert_eq!(iter.by_ref().count(), 3);
+            assert!(iter.next().is_none());
+        });
+    }
+
+    #[test]
+    fn empty_pool_iterates_nothing() {
+        with_pool(&[], |pool| assert!(pool.iter().next().is_none()));
+    }
+
+    #[test]
+    fn empty_pool_totals() {
+        with_pool(&[], |pool| {
+            assert_eq!(pool.total_free_space_in_bytes(), 0);
+            assert_eq!(pool.total_capacity(), 0);
+            assert_eq!(pool.total_free_space_in_objects(), 0);
+        });
+    }
+
+    #[test]
+    fn pool_totals() {
+        let fakes = [
+            FakeStorage::new(1, 1000, 250).free_objects(10),
+            FakeStorage::new(2, 500, 500).free_objects(20),
+            FakeStorage::new(3, 0, 0),
+        ];
+
+        with_pool(&fakes, |pool| {
+            assert_eq!(pool.total_free_space_in_bytes(), 750);
+            assert_eq!(pool.total_capacity(), 1500);
+            assert_eq!(pool.total_free_space_in_objects(), 30);
+        });
+    }
+
+    #[test]
+    fn pool_totals_saturate() {
+        let fakes = [
+            FakeStorage::new(1, u64::MAX, 1).free_objects(u64::MAX),
+            FakeStorage::new(2, 1, 1).free_objects(1),
+        ];
+
+        with_pool(&fakes, |pool| {
+            assert_eq!(pool.total_capacity(), u64::MAX);
+            assert_eq!(pool.total_free_space_in_objects(), u64::MAX);
+            assert_eq!(pool.total_free_space_in_bytes(), 2);
+        });
+    }
+
+    #[test]
+    fn most_free_space_skips_read_only_storages() {
+        let fakes = [
+            FakeStorage::new(1, 1000, 100),
+            FakeStorage::new(2, 9000, 9000).access(AccessCapability::ReadOnly),
+            FakeStorage::new(3, 1000, 600),
+            FakeStorage::new(4, 9000, 8000).access(AccessCapability::ReadOnlyWithObjectDeletion),
+        ];
+
+        with_pool(&fakes, |pool| {
+            let best = pool.storage_with_most_free_space().map(Storage::id);
+            assert_eq!(best, Some(3));
+        });
+    }
+
+    #[test]
+    fn no_most_free_space_without_writable_storages() {
+        let fakes = [FakeStorage::new(1, 1000, 1000).access(AccessCapability::ReadOnly)];
+
+        with_pool(&fakes, |pool| {
+            assert!(pool.storage_with_most_free_space().is_none())
+        });
+
+        with_pool(&[], |pool| {
+            assert!(pool.storage_with_most_free_space().is_none())
+        });
+    }
+
+    #[test]
+    fn read_write_storage() {
+        let fake = FakeStorage::new(1, 0, 0).access(AccessCapability::ReadWrite);
+
+        with_storage(fake, |storage| {
+            assert!(storage.is_writable());
+            assert!(storage.allows_deletion());
+            assert!(storage.check_writable().is_ok());
+        });
+    }
+
+    #[test]
+    fn read_only_storage() {
+        let fake = FakeStorage::new(7, 0, 0).access(AccessCapability::ReadOnly);
+
+        with_storage(fake, |storage| {
+            assert!(!storage.is_writable());
+            assert!(!storage.allows_deletion());
+            assert!(matches!(
+                storage.check_writable(),
+                Err(Error::ReadOnlyStorage { storage_id: 7 })
+            ));
+        });
+    }
+
+    #[test]
+    fn read_only_storage_with_object_deletion() {
+        let fake = FakeStorage::new(7, 0, 0).access(AccessCapability::ReadOnlyWithObjectDeletion);
+
+        with_storage(fake, |storage| {
+            assert!(!storage.is_writable());
+            assert!(storage.allows_deletion());
+            assert!(matches!(
+                storage.check_writable(),
+                Err(Error::ReadOnlyStorage { storage_id: 7 })
+            ));
+        });
+    }
+
+    #[test]
+    fn free_space_ratio_with_zero_capacity() {
+        with_storage(FakeStorage::new(1, 0, 0), |storage| {
+            assert_eq!(storage.free_space_ratio(), 0.0);
+            assert_eq!(storage.used_space_in_bytes(), 0);
+            assert!(storage.is_full());
+        });
+
+        // Some devices report more free space than capacity.
+        with_storage(FakeStorage::new(1, 0, 4096), |storage| {
+            assert_eq!(storage.free_space_ratio(), 0.0);
+            assert_eq!(storage.used_space_in_bytes(), 0);
+        });
+    }
+
+    #[test]
+    fn free_space_ratio() {
+        let gib = 1024 * 1024 * 1024;
+
+        with_storage(FakeStorage::new(1, 4 * gib, gib), |storage| {
+            assert_eq!(storage.free_space_ratio(), 0.25);
+            assert_eq!(storage.used_space_in_bytes(), 3 * gib);
+            assert!(!storage.is_full());
+        });
+
+        with_storage(FakeStorage::new(1, gib, 2 * gib), |storage| {
+            assert_eq!(storage.free_space_ratio(), 1.0);
+            assert_eq!(storage.used_space_in_bytes(), 0);
+        });
+    }
+
+    #[test]
+    fn full_under_threshold() {
+        let max = 8 * FULL_STORAGE_THRESHOLD;
+
+        with_storage(
+            FakeStorage::new(1, max, FULL_STORAGE_THRESHOLD - 1),
+            |storage| assert!(storage.is_full()),
+        );
+
+        with_storage(
+            FakeStorage::new(1, max, FULL_STORAGE_THRESHOLD),
+            |storage| assert!(!storage.is_full()),
+        );
+    }
+
+    #[test]
+    fn folder_names_with_nul_bytes_are_rejected() {
+        let device = MtpDevice::fake();
+        let res = create_folder(&device, "bad\0name", Parent::Root, 0);
+
+        assert!(matches!(res, Err(Error::InvalidString { .. })));
+    }
+
+    #[test]
+    fn write_refusals() {
+        let mtp_error = |kind, text: &str| Error::MtpError {
+            kind,
+            text: text.to_string(),
+        };
+
+        assert!(is_write_refusal(&mtp_error(MtpErrorKind::StorageFull, "")));
+        assert!(is_write_refusal(&mtp_error(
+            MtpErrorKind::PtpLayer,
+            "Error 200c: PTP Store Full"
+        )));
+        assert!(is_write_refusal(&mtp_error(
+            MtpErrorKind::PtpLayer,
+            "Error 200e: PTP Store Read Only"
+        )));
+        assert!(is_write_refusal(&mtp_error(
+            MtpErrorKind::PtpLayer,
+            "Error 200f: PTP Access Denied"
+        )));
+
+        assert!(!is_write_refusal(&mtp_error(
+            MtpErrorKind::PtpLayer,
+            "Error 2002: PTP General Error"
+        )));
+        assert!(!is_write_refusal(&mtp_error(MtpErrorKind::UsbLayer, "")));
+        assert!(!is_write_refusal(&Error::Unknown));
+    }
 }
@@ -0,0 +1,329 @@
+//! Contains relevant items to handle album objects in the device.
+
+use std::fmt::{self, Debug};
+
+use libmtp_sys as ffi;
+
+use crate::device::MtpDevice;
+use crate::object::properties::Property;
+use crate::object::samples::SampleData;
+use crate::object::{AsObjectId, Object};
+use crate::storage::files::{self, File};
+use crate::storage::Parent;
+use crate::util::{optional_str, optional_strdup, u32_slice_to_c_array};
+use crate::Result;
+
+/// Abstraction of an album object, it implements `Object`. An album groups tracks by their ids,
+/// note that the list of tracks is the one gathered when this album was retrieved.
+pub struct Album<'a> {
+    pub(crate) inner: *mut ffi::LIBMTP_album_t,
+    pub(crate) owner: &'a MtpDevice,
+}
+
+impl Drop for Album<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::LIBMTP_destroy_album_t(self.inner);
+        }
+    }
+}
+
+impl Object for Album<'_> {
+    fn id(&self) -> u32 {
+        unsafe { (*self.inner).album_id }
+    }
+
+    fn device(&self) -> &MtpDevice {
+        self.owner
+    }
+}
+
+impl Object for &Album<'_> {
+    fn id(&self) -> u32 {
+        unsafe { (*self.inner).album_id }
+    }
+
+    fn device(&self) -> &MtpDevice {
+        self.owner
+    }
+}
+
+impl Debug for Album<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Album")
+            .field("id", &self.id())
+            .field("name", &self.name())
+            .field("artist", &self.artist())
+            .field("tracks", &self.tracks())
+            .finish()
+    }
+}
+
+impl<'a> Album<'a> {
+    /// Returns the name of this album, if any.
+    pub fn name(&self) -> Option<&str> {
+        unsafe { optional_str((*self.inner).name) }
+    }
+
+    /// Returns the artist of this album, if any.
+    pub fn artist(&self) -> Option<&str> {
+        unsafe { optional_str((*self.inner).artist) }
+    }
+
+    /// Returns the composer of this album, if any.
+    pub fn composer(&self) -> Option<&str> {
+        unsafe { optional_str((*self.inner).composer) }
+    }
+
+    /// Returns the genre of this album, if any.
+    pub fn genre(&self) -> Option<&str> {
+        unsafe { optional_str((*self.inner).genre) }
+    }
+
+    /// Retrieves the cover art of this album (its representative sample) together with its
+    /// filetype and size, returns `Ok(None)` if the album doesn't have one. See
+    /// `Object::get_representative_sample`.
+    pub fn cover_art(&self) -> Result<Option<SampleData>> {
+        if let Ok(0) = self.get_u32(Property::RepresentativeSampleSize) {
+            return Ok(None);
+        }
+
+        let sample = self.get_representative_sample()?;
+
+        if sample.data.is_empty() {
+            Ok(None)
+        } else {
+            Ok(Some(sample))
+        }
+    }
+
+    /// Returns the ids of the tracks in this album.
+    pub fn tracks(&self) -> &[u32] {
+        unsafe {
+            let tracks = (*self.inner).tracks;
+            let len = (*self.inner).no_tracks as usize;
+
+            if tracks.is_null() || len == 0 {
+                &[]
+            } else {
+                std::slice::from_raw_parts(tracks, len)
+            }
+        }
+    }
+
+    /// Adds a track to this album and updates the album in the device, adding a track that
+    /// is already in this album is a no-op.
+    pub fn add_track(&mut self, track: impl AsObjectId) -> Result<()> {
+        match with_track(self.tracks(), track.as_id()) {
+            Some(tracks) => self.update_album(&tracks),
+            None => Ok(()),
+        }
+    }
+
+    /// Removes a track from this album and updates the album in the device, removing a track
+    /// that isn't in this album is a no-op.
+    pub fn remove_track(&mut self, track: impl AsObjectId) -> Result<()> {
+        match without_track(self.tracks(), track.as_id()) {
+            Some(tracks) => self.update_album(&tracks),
+            None => Ok(()),
+        }
+    }
+
+    /// Retrieves the files of the tracks referenced by this album. If `skip_missing` is `true`
+    /// the tracks that can't be retrieved (e.g. deleted) are skipped, otherwise the first error
+    /// is returned.
+    pub fn referenced_files(&self, skip_missing: bool) -> Result<Vec<File<'a>>> {
+        files::files_from_ids(self.owner, self.tracks(), skip_missing)
+    }
+
+    /// Replaces the tracks of this album and updates it in the device, on failure the
+    /// previous list of tracks is kept.
+    pub fn update_album(&mut self, tracks: &[u32]) -> Result<()> {
+        unsafe {
+            let new_tracks = u32_slice_to_c_array(tracks);
+
+            let old_tracks = (*self.inner).tracks;
+            let old_len = (*self.inner).no_tracks;
+
+            (*self.inner).tracks = new_tracks;
+            (*self.inner).no_tracks = tracks.len() as u32;
+
+            let res = ffi::LIBMTP_Update_Album(self.owner.inner, self.inner);
+
+            if res != 0 {
+                (*self.inner).tracks = old_tracks;
+                (*self.inner).no_tracks = old_len;
+                libc::free(new_tracks as *mut _);
+
+                Err(self.owner.latest_error().unwrap_or_default())
+            } else {
+                libc::free(old_tracks as *mut _);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Returns the tracks with `track` appended, or `None` if it's already there.
+fn with_track(tracks: &[u32], track: u32) -> Option<Vec<u32>> {
+    if tracks.contains(&track) {
+        return None;
+    }
+
+    let mut tracks = tracks.to_vec();
+    tracks.push(track);
+    Some(tracks)
+}
+
+/// Returns the tracks without `track`, or `None` if it isn't there.
+fn without_track(tracks: &[u32], track: u32) -> Option<Vec<u32>> {
+    if !tracks.contains(&track) {
+        return None;
+    }
+
+    Some(tracks.iter().copied().filter(|&t| t != track).collect())
+}
+
+pub(crate) fn get_album(mtpdev: &MtpDevice, album: impl AsObjectId) -> Result<Album<'_>> {
+    let album = unsafe { ffi::LIBMTP_Get_Album(mtpdev.inner, album.as_id()) };
+
+    if album.is_null() {
+        Err(mtpdev.latest_error().unwrap_or_default())
+    } else {
+        Ok(Album {
+            inner: album,
+            owner: mtpdev,
+        })
+    }
+}
+
+/// Convenience struct used as a parameter to create albums, the optional strings are only sent
+/// when they're given.
+#[derive(Debug, Clone)]
+pub struct AlbumMetadata<'a> {
+    pub name: &'a str,
+    pub artist: Option<&'a str>,
+    pub composer: Option<&'a str>,
+    pub genre: Option<&'a str>,
+}
+
+pub(crate) fn album_list(mtpdev: &MtpDevice, storage_id: u32) -> Vec<Album<'_>> {
+    let mut head = if storage_id == 0 {
+        unsafe { ffi::LIBMTP_Get_Album_List(mtpdev.inner) }
+    } else {
+        unsafe { ffi::LIBMTP_Get_Album_List_For_Storage(mtpdev.inner, storage_id) }
+    };
+
+    let mut albums = Vec::new();
+    while !head.is_null() {
+        albums.push(Album {
+            inner: head,
+            owner: mtpdev,
+        });
+
+        head = unsafe { (*head).next };
+    }
+
+    albums
+}
+
+pub(crate) fn create_album<'a>(
+    mtpdev: &'a MtpDevice,
+    storage_id: u32,
+    parent: Parent,
+    metadata: AlbumMetadata<'_>,
+    tracks: &[u32],
+) -> Result<Album<'a>> {
+    let album = unsafe { ffi::LIBMTP_new_album_t() };
+    let album = Album {
+        inner: album,
+        owner: mtpdev,
+    };
+
+    unsafe {
+        let inner = album.inner;
+
+        (*inner).parent_id = parent.to_id();
+        (*inner).storage_id = storage_id;
+        (*inner).name = optional_strdup(Some(metadata.name))?;
+        (*inner).artist = optional_strdup(metadata.artist)?;
+        (*inner).composer = optional_strdup(metadata.composer)?;
+        (*inner).genre = optional_strdup(metadata.genre)?;
+
+        if !tracks.is_empty() {
+            (*inner).tracks = u32_slice_to_c_array(tracks);
+            (*inner).no_tracks = tracks.len() as u32;
+        }
+    }
+
+    let res = unsafe { ffi::LIBMTP_Create_New_Album(mtpdev.inner, album.inner) };
+
+    if res != 0 {
+        Err(mtpdev.latest_error().unwrap_or_default())
+    } else {
+        Ok(album)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn adds_tracks() {
+        assert_eq!(with_track(&[], 1), Some(vec![1]));
+        assert_eq!(with_track(&[1, 2], 3), Some(vec![1, 2, 3]));
+    }
+
+    #[test]
+    fn adding_a_present_track_is_a_no_op() {
+        assert_eq!(with_track(&[1, 2], 2), None);
+    }
+
+    #[test]
+    fn removes_tracks() {
+        assert_eq!(without_track(&[1, 2, 3], 2), Some(vec![1, 3]));
+        assert_eq!(without_track(&[1], 1), Some(vec![]));
+    }
+
+    #[test]
+    fn removing_a_missing_track_is_a_no_op() {
+        assert_eq!(without_track(&[], 1), None);
+        assert_eq!(without_track(&[1, 2], 3), None);
+    }
+
+    #[test]
+    #[ignore = "needs a connected MTP device with an audio file"]
+    fn adds_and_removes_a_track() {
+        use crate::device::raw::detect_raw_devices;
+        use crate::device::StorageSort;
+        use crate::storage::Parent;
+
+        let raw = detect_raw_devices().unwrap().remove(0);
+        let mut device = raw.try_open_uncached().unwrap();
+        device.update_storage(StorageSort::NotSorted).unwrap();
+
+        let pool = device.storage_pool();
+        let track = pool
+            .walk(Parent::Root)
+            .find(|file| file.ftype().is_audio())
+            .unwrap()
+            .id();
+
+        let metadata = AlbumMetadata {
+            name: "libmtp-rs test album",
+            artist: None,
+            composer: None,
+            genre: None,
+        };
+        let mut album = pool.create_album(metadata, &[], Parent::Root).unwrap();
+
+        album.add_track(track).unwrap();
+        assert_eq!(pool.get_album(album.id()).unwrap().tracks(), [track]);
+
+        album.remove_track(track).unwrap();
+        assert!(pool.get_album(album.id()).unwrap().tracks().is_empty());
+
+        album.delete().unwrap();
+    }
+}
@@ -0,0 +1,206 @@
+//! Async wrappers for transfers of a single storage, only available with the `async` feature.
+//!
+//! The transfers run on background threads (see `MtpDevice` to know why the device can be moved
+//! between threads), so they don't block the async executor and don't depend on any particular
+//! async runtime. Progress is reported through a `ProgressReceiver` instead of a synchronous
+//! callback.
+
+use std::future::{self, Future};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use chrono::{DateTime, Utc};
+
+use crate::device::MtpDevice;
+use crate::error::Error;
+use crate::object::filetypes::Filetype;
+use crate::object::{AsObjectId, Object};
+use crate::storage::files::{self, FileMetadata};
+use crate::storage::Parent;
+use crate::util::{self, lock, spawn_blocking, CallbackReturn};
+use crate::Result;
+
+/// Owning handle to a storage of a device, unlike `Storage` it doesn't borrow the device, so its
+/// transfers can be moved to background threads. The device is shared behind a mutex, thus the
+/// transfers of all the handles of a device are performed one at a time.
+#[derive(Debug, Clone)]
+pub struct AsyncStorage {
+    device: Arc<Mutex<MtpDevice>>,
+    storage_id: u32,
+}
+
+impl AsyncStorage {
+    /// Takes ownership of the device, to handle the storage with the given id.
+    pub fn new(device: MtpDevice, storage_id: u32) -> Self {
+        Self::from_shared(Arc::new(Mutex::new(device)), storage_id)
+    }
+
+    /// Handles the storage with the given id of an already shared device, useful to handle many
+    /// storages of the same device (see [`device`](#method.device)).
+    pub fn from_shared(device: Arc<Mutex<MtpDevice>>, storage_id: u32) -> Self {
+        AsyncStorage { device, storage_id }
+    }
+
+    /// Returns the shared device, it can be locked to make other requests.
+    pub fn device(&self) -> &Arc<Mutex<MtpDevice>> {
+        &self.device
+    }
+
+    /// Returns the id of the handled storage.
+    pub fn id(&self) -> u32 {
+        self.storage_id
+    }
+
+    /// Async version of `Storage::get_file_to_path`.
+    pub async fn get_file_to_path_async(
+        &self,
+        file: impl AsObjectId,
+        path: impl Into<PathBuf>,
+    ) -> Result<()> {
+        let device = Arc::clone(&self.device);
+        let file = file.as_id();
+        let path = path.into();
+
+        spawn_blocking(move || files::get_file_to_path(&lock(&device), file, path)).await
+    }
+
+    /// Same as [`get_file_to_path_async`](#method.get_file_to_path_async), but the progress is
+    /// forwarded to the returned `ProgressReceiver`, which ends once the transfer ends. The
+    /// transfer starts right away, even if the future isn't awaited yet, and dropping the
+    /// receiver doesn't cancel it.
+    pub fn get_file_to_path_with_progress(
+        &self,
+        file: impl AsObjectId,
+        path: impl Into<PathBuf>,
+    ) -> (ProgressReceiver, impl Future<Output = Result<()>>) {
+        let device = Arc::clone(&self.device);
+        let file = file.as_id();
+        let path = path.into();
+        let (sender, receiver) = util::channel();
+
+        let transfer = spawn_blocking(move || {
+            files::get_file_to_path_with_callback(&lock(&device), file, path, |sent, total| {
+                sender.send((sent, total));
+                CallbackReturn::Continue
+            })
+        });
+
+        (ProgressReceiver { receiver }, transfer)
+    }
+
+    /// Async version of `Storage::send_file_from_path`, the metadata is taken from the local
+    /// file (its name, size and modification date). Returns the id of the new file.
+    pub async fn send_file_from_path_async(
+        &self,
+        path: impl Into<PathBuf>,
+        parent: Parent,
+        file_type: Filetype,
+    ) -> Result<u32> {
+        let device = Arc::clone(&self.device);
+        let storage_id = self.storage_id;
+        let path = path.into();
+
+        spawn_blocking(move || {
+            let local = std::fs::metadata(&path)?;
+            let file_name = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .ok_or(Error::InvalidMetadata {
+                    reason: "the local file name isn't valid UTF-8",
+                })?
+                .to_string();
+
+            let metadata = FileMetadata {
+                file_size: local.len(),
+                file_name: &file_name,
+                file_type,
+                modification_date: local.modified().map(DateTime::<Utc>::from)?,
+            };
+
+            let device = lock(&device);
+            let pool = device.storage_pool();
+
+            if matches!(pool.by_id(storage_id), Some(storage) if !storage.is_writable()) {
+                return Err(Error::ReadOnlyStorage { storage_id });
+            }
+
+            let file = files::send_file_from_path(&device, storage_id, &path, parent, metadata)?;
+            Ok(file.id())
+        })
+        .await
+    }
+}
+
+/// Progress of a transfer as `(sent_bytes, total_bytes)`, see
+/// `AsyncStorage::get_file_to_path_with_progress`. Waiting for the next update wakes the task
+/// instead of blocking the thread, so it can be awaited from any async runtime.
+#[derive(Debug)]
+pub struct ProgressReceiver {
+    receiver: util::Receiver<(u64, u64)>,
+}
+
+impl ProgressReceiver {
+    /// Waits for the next update, returns `None` once the transfer ended and every update was
+    /// received.
+    pub async fn recv(&mut self) -> Option<(u64, u64)> {
+        future::poll_fn(|cx| self.poll_recv(cx)).await
+    }
+
+    /// Polls for the next update, useful to build a stream on top of the receiver.
+    pub fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<(u64, u64)>> {
+        self.receiver.poll_recv(cx)
+    }
+
+    /// Returns the next update if there's one already, without waiting.
+    pub fn try_recv(&mut self) -> Option<(u64, u64)> {
+        self.receiver.try_recv()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::device::raw::detect_raw_devices;
+    use crate::device::StorageSort;
+    use crate::util::block_on;
+
+    #[test]
+    #[ignore = "needs a connected MTP device with a file under 1 MiB"]
+    fn downloads_a_file_with_progress() {
+        let raw = detect_raw_devices().unwrap().remove(0);
+        let mut device = raw.try_open_uncached().unwrap();
+        device.update_storage(StorageSort::NotSorted).unwrap();
+
+        let (storage_id, file_id, size) = {
+            let pool = device.storage_pool();
+            let (_, storage) = pool.iter().next().unwrap();
+            let file = storage
+                .walk(Parent::Root)
+                .find(|file| file.ftype() != Filetype::Folder && file.size() <= 1 << 20)
+                .unwrap();
+
+            (storage.id(), file.id(), file.size())
+        };
+
+        let path = std::env::temp_dir().join(format!("libmtp-rs-async-{}", std::process::id()));
+        let storage = AsyncStorage::new(device, storage_id);
+        let (mut progress, transfer) = storage.get_file_to_path_with_progress(file_id, &path);
+
+        let updates = block_on(async {
+            let mut updates = Vec::new();
+            while let Some(update) = progress.recv().await {
+                updates.push(update);
+            }
+
+            transfer.await.map(|_| updates)
+        })
+        .unwrap();
+
+        assert_eq!(std::fs::metadata(&path).unwrap().len(), size);
+        assert!(updates
+            .iter()
+            .all(|&(sent, total)| sent <= total && total == size));
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -3,21 +3,28 @@
 use chrono::{DateTime, TimeZone, Utc};
 use libmtp_sys as ffi;
 use num_traits::FromPrimitive;
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::HashSet;
 use std::ffi::{CStr, CString};
 use std::fmt::{self, Debug};
+use std::io::Read;
 use std::path::Path;
 
 #[cfg(unix)]
 use std::os::unix::io::AsRawFd;
 
+use crate::device::capabilities::DeviceCapability;
 use crate::device::MtpDevice;
+use crate::error::Error;
 use crate::object::filetypes::Filetype;
 use crate::object::{AsObjectId, Object};
+use crate::storage::folders::sanitize_folder_name;
 use crate::storage::Parent;
 use crate::util::data_get_func_handler;
 use crate::util::data_put_func_handler;
 use crate::util::progress_func_handler;
-use crate::util::{CallbackReturn, HandlerReturn};
+use crate::util::{CallbackReturn, HandlerReturn, Throttle, TransferOutcome};
 use crate::Result;
 
 /// Abstraction of a file object, it implements `Object`, you may want to use
@@ -69,6 +76,77 @@ impl Debug for File<'_> {
     }
 }
 
+/// Two files are equal if they have the same id and belong to the same device (see
+/// `DeviceId`), the rest of the metadata isn't compared.
+impl PartialEq for File<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.id() == other.id() && self.device_id() == other.device_id()
+    }
+}
+
+impl Eq for File<'_> {}
+
+/// Files are ordered by their name (case-insensitive), ties are broken by the exact name and then
+/// by device and id, so the ordering is consistent with equality.
+impl Ord for File<'_> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let lowercase = |file: &Self| file.name().to_lowercase();
+
+        lowercase(self)
+            .cmp(&lowercase(other))
+            .then_with(|| self.name().cmp(&other.name()))
+            .then_with(|| self.device_id().cmp(&other.device_id()))
+            .then_with(|| self.id().cmp(&other.id()))
+    }
+}
+
+impl PartialOrd for File<'_> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<'a> File<'a> {
+    /// Retrieves the metadata of the file with the given id, this is useful to rebuild a `File`
+    /// from an id you stored before (e.g. across reconnections), note that ids are unique across
+    /// all the device. Returns `Error::ObjectNotFound` if there isn't a file with such id.
+    pub fn from_id(mtpdev: &'a MtpDevice, id: impl AsObjectId) -> Result<File<'a>> {
+        let id = id.as_id();
+        let file = unsafe { ffi::LIBMTP_Get_Filemetadata(mtpdev.inner, id) };
+
+        if file.is_null() {
+            Err(mtpdev
+                .latest_error()
+                .unwrap_or(Error::ObjectNotFound { id }))
+        } else {
+            Ok(File {
+                inner: file,
+                owner: mtpdev,
+            })
+        }
+    }
+}
+
+/// Rebuilds the files with the given ids, if `skip_missing` is `true` the ids that can't be
+/// retrieved are skipped, otherwise the first error is returned.
+pub(crate) fn files_from_ids<'a>(
+    mtpdev: &'a MtpDevice,
+    ids: &[u32],
+    skip_missing: bool,
+) -> Result<Vec<File<'a>>> {
+    let mut files = Vec::with_capacity(ids.len());
+
+    for &id in ids {
+        match File::from_id(mtpdev, id) {
+            Ok(file) => files.push(file),
+            Err(_) if skip_missing => continue,
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(files)
+}
+
 impl File<'_> {
     /// Returns the id of the storage it belongs to.
     pub fn storage_id(&self) -> u32 {
@@ -92,10 +170,10 @@ impl File<'_> {
     }
 
     /// Returns the name of this file.
-    pub fn name(&self) -> &str {
+    pub fn name(&self) -> Cow<'_, str> {
         unsafe {
             let cstr = CStr::from_ptr((*self.inner).filename);
-            cstr.to_str().expect("Invalid UTF-8 on file name")
+            cstr.to_string_lossy()
         }
     }
 
@@ -113,7 +191,7 @@ impl File<'_> {
 
     /// Rename this file in-place.
     pub fn rename(&mut self, new_name: &str) -> Result<()> {
-        let new_name = CString::new(new_name).expect("Nul byte");
+        let new_name = CString::new(new_name)?;
 
         let res = unsafe {
             ffi::LIBMTP_Set_File_Name(self.owner.inner, self.inner, new_name.as_ptr() as *const _)
@@ -125,6 +203,107 @@ impl File<'_> {
             Ok(())
         }
     }
+
+    /// Deletes this file off the device like `Object::delete`, but consumes the handle so it
+    /// can't be used with an invalid id afterwards. Note that the handle is dropped even if the
+    /// deletion fails.
+    pub fn delete_consume(self) -> Result<()> {
+        Object::delete(&self)
+    }
+}
+
+impl<'a> File<'a> {
+    /// Begins an edit session on this file, letting you read, write and truncate it in place,
+    /// see `EditSession`. Returns `Error::UnsupportedCapability` if the device doesn't support
+    /// `DeviceCapability::EditObjects`.
+    pub fn open_edit(&mut self) -> Result<EditSession<'_, 'a>> {
+        let device = self.owner;
+
+        if !device.check_capability(DeviceCapability::EditObjects) {
+            return Err(Error::UnsupportedCapability {
+                capability: DeviceCapability::EditObjects,
+            });
+        }
+
+        let res = unsafe { ffi::LIBMTP_BeginEditObject(device.inner, self.id()) };
+
+        if res != 0 {
+            Err(device.latest_error().unwrap_or_default())
+        } else {
+            Ok(EditSession {
+                file: self,
+                finished: false,
+            })
+        }
+    }
+}
+
+/// In place edit session of a file, created with
+/// [`File::open_edit`](struct.File.html#method.open_edit). The session ends when
+/// [`finish`](struct.EditSession.html#method.finish) is called or when it's dropped (ignoring
+/// errors), after that the metadata of the file (e.g. its size) is refreshed.
+pub struct EditSession<'f, 'a> {
+    file: &'f mut File<'a>,
+    finished: bool,
+}
+
+impl Debug for EditSession<'_, '_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("EditSession")
+            .field("file", &self.file)
+            .finish()
+    }
+}
+
+impl Drop for EditSession<'_, '_> {
+    fn drop(&mut self) {
+        if !self.finished {
+            let _ = self.end();
+        }
+    }
+}
+
+impl EditSession<'_, '_> {
+    /// Reads at most `maxbytes` of the file starting at `offset`.
+    pub fn read_at(&self, offset: u64, maxbytes: u32) -> Result<Vec<u8>> {
+        self.file.get_partial_object(offset, maxbytes)
+    }
+
+    /// Writes `data` into the file starting at `offset`.
+    pub fn write_at(&mut self, offset: u64, data: impl AsRef<[u8]>) -> Result<()> {
+        self.file.send_partial_object(offset, data)
+    }
+
+    /// Truncates the file to `size` bytes.
+    pub fn truncate(&mut self, size: u64) -> Result<()> {
+        let device = self.file.owner;
+        let res = unsafe { ffi::LIBMTP_TruncateObject(device.inner, self.file.id(), size) };
+
+        if res != 0 {
+            Err(device.latest_error().unwrap_or_default())
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Ends this session and refreshes the metadata of the file.
+    pub fn finish(mut self) -> Result<()> {
+        self.finished = true;
+        self.end()
+    }
+
+    fn end(&mut self) -> Result<()> {
+        let device = self.file.owner;
+        let id = self.file.id();
+
+        let res = unsafe { ffi::LIBMTP_EndEditObject(device.inner, id) };
+        if res != 0 {
+            return Err(device.latest_error().unwrap_or_default());
+        }
+
+        *self.file = File::from_id(device, id)?;
+        Ok(())
+    }
 }
 
 /// Convenience struct used as a parameter to send local files to an MTP device.
@@ -136,6 +315,82 @@ pub struct FileMetadata<'a> {
     pub modification_date: DateTime<Utc>,
 }
 
+impl FileMetadata<'_> {
+    /// Checks that this metadata is consistent before sending a file with it, i.e. the file name
+    /// isn't empty and doesn't contain nul bytes, returns `Error::InvalidMetadata` otherwise.
+    ///
+    /// Every send function calls this automatically, sends from a path also check that
+    /// `file_size` matches the size of the local file.
+    pub fn validate(&self) -> Result<()> {
+        if self.file_name.is_empty() {
+            Err(Error::InvalidMetadata {
+                reason: "empty file name",
+            })
+        } else if self.file_name.contains('\0') {
+            Err(Error::InvalidMetadata {
+                reason: "file name contains a nul byte",
+            })
+        } else {
+            Ok(())
+        }
+    }
+
+    /// Validates this metadata for a send from a local file in `path`, the local file is checked
+    /// beforehand so a missing or unreadable file fails with `Error::Io` instead of a device
+    /// error.
+    pub(crate) fn validate_for_path(&self, path: &Path) -> Result<()> {
+        self.validate()?;
+
+        let local = std::fs::metadata(path)?;
+
+        if local.len() != self.file_size {
+            Err(Error::InvalidMetadata {
+                reason: "file size doesn't match the size of the local file",
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Checks that an object of `size` bytes isn't larger than the capacity of the given storage
+/// (`0` meaning any storage of the device), see `MtpDevice::max_storage_capacity`.
+pub(crate) fn check_storage_capacity(mtpdev: &MtpDevice, storage_id: u32, size: u64) -> Result<()> {
+    let max = if storage_id == 0 {
+        mtpdev.max_storage_capacity()
+    } else {
+        mtpdev
+            .storage_pool()
+            .by_id(storage_id)
+            .map(|storage| storage.maximum_capacity())
+            .filter(|&capacity| capacity > 0)
+    };
+
+    match max {
+        Some(max) if size > max => Err(Error::ObjectTooLarge { size, max }),
+        _ => Ok(()),
+    }
+}
+
+/// Handles a failed send, retrieves the error and destroys `file_t`, if the device was set to
+/// remove failed sends it also deletes the incomplete object (if it was created).
+fn failed_send(mtpdev: &MtpDevice, file_t: *mut ffi::LIBMTP_file_t) -> Error {
+    let err = mtpdev.latest_error().unwrap_or_default();
+
+    unsafe {
+        let id = (*file_t).item_id;
+
+        if mtpdev.remove_failed_sends() && id != 0 {
+            ffi::LIBMTP_Delete_Object(mtpdev.inner, id);
+            let _ = mtpdev.latest_error();
+        }
+
+        ffi::LIBMTP_destroy_file_t(file_t);
+    }
+
+    err
+}
+
 pub(crate) fn get_file_to_path(
     mtpdev: &MtpDevice,
     file: impl AsObjectId,
@@ -161,18 +416,34 @@ pub(crate) fn get_file_to_path(
     }
 }
 
-pub(crate) fn get_file_to_path_with_callback<C>(
+pub(crate) fn get_file_to_path_create_dirs(
+    mtpdev: &MtpDevice,
+    file: impl AsObjectId,
+    path: impl AsRef<Path>,
+) -> Result<()> {
+    let path = path.as_ref();
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    get_file_to_path(mtpdev, file, path)
+}
+
+pub(crate) fn get_file_to_path_with_callback<C, R>(
     mtpdev: &MtpDevice,
     file: impl AsObjectId,
     path: impl AsRef<Path>,
     mut callback: C,
 ) -> Result<()>
 where
-    C: FnMut(u64, u64) -> CallbackReturn,
+    C: FnMut(u64, u64) -> R,
+    R: Into<CallbackReturn>,
 {
     let path = path.as_ref();
     let path = path_to_cvec!(path);
 
+    let mut callback = |sent, total| callback(sent, total).into();
     let mut callback: &mut dyn FnMut(u64, u64) -> CallbackReturn = &mut callback;
     let callback = &mut callback as *mut _ as *mut libc::c_void as *const _;
 
@@ -193,6 +464,95 @@ where
     }
 }
 
+/// Local names of the files retrieved by `get_files_to_dir`, sanitized with
+/// `sanitize_folder_name` so every file stays directly under the directory. Names that are still
+/// invalid or that collide with each other are an error, before anything is retrieved.
+fn local_file_names<'n>(names: impl IntoIterator<Item = &'n str>) -> Result<Vec<String>> {
+    let mut seen = HashSet::new();
+
+    names
+        .into_iter()
+        .map(|name| {
+            let local = sanitize_folder_name(name);
+
+            if matches!(local.as_str(), "" | "." | "..") {
+                return Err(Error::InvalidFileName {
+                    name: name.to_string(),
+                });
+            }
+
+            if !seen.insert(local.clone()) {
+                return Err(Error::DuplicateFileName { name: local });
+            }
+
+            Ok(local)
+        })
+        .collect()
+}
+
+pub(crate) fn get_files_to_dir<C, R>(
+    mtpdev: &MtpDevice,
+    files: &[impl AsObjectId],
+    dir: impl AsRef<Path>,
+    mut callback: C,
+) -> Result<usize>
+where
+    C: FnMut(usize, usize, u64, u64) -> R,
+    R: Into<CallbackReturn>,
+{
+    let dir = dir.as_ref();
+    let files = files
+        .iter()
+        .map(|file| File::from_id(mtpdev, file.as_id()))
+        .collect::<Result<Vec<_>>>()?;
+    let names = files.iter().map(File::name).collect::<Vec<_>>();
+    let names = local_file_names(names.iter().map(|name| name.as_ref()))?;
+
+    let files_total = files.len();
+    let total: u64 = files.iter().map(File::size).sum();
+    let mut done = 0;
+
+    for (index, (file, name)) in files.iter().zip(names).enumerate() {
+        let path = dir.join(name);
+        let mut cancelled = false;
+
+        let res = get_file_to_path_with_callback(mtpdev, file, &path, |sent, _| {
+            let ret = callback(index, files_total, done + sent, total).into();
+            cancelled = matches!(ret, CallbackReturn::Cancel);
+            ret
+        });
+
+        if cancelled {
+            // Cancelling on the last update doesn't abort the transfer, so the file is complete.
+            if res.is_ok() {
+                return Ok(index + 1);
+            }
+
+            // `libmtp` removes partial files itself, make sure none is left behind anyway.
+            let _ = std::fs::remove_file(&path);
+            return Ok(index);
+        }
+
+        res?;
+        done += file.size();
+    }
+
+    Ok(files_total)
+}
+
+pub(crate) fn get_file_to_path_throttled(
+    mtpdev: &MtpDevice,
+    file: impl AsObjectId,
+    path: impl AsRef<Path>,
+    max_bytes_per_sec: u64,
+) -> Result<()> {
+    let mut throttle = Throttle::new(max_bytes_per_sec);
+    get_file_to_path_with_callback(mtpdev, file, path, |sent, _| {
+        throttle.wait(sent);
+        CallbackReturn::Continue
+    })
+}
+
 #[cfg(unix)]
 pub(crate) fn get_file_to_descriptor(
     mtpdev: &MtpDevice,
@@ -217,15 +577,17 @@ pub(crate) fn get_file_to_descriptor(
 }
 
 #[cfg(unix)]
-pub(crate) fn get_file_to_descriptor_with_callback<C>(
+pub(crate) fn get_file_to_descriptor_with_callback<C, R>(
     mtpdev: &MtpDevice,
     file: impl AsObjectId,
     descriptor: impl AsRawFd,
     mut callback: C,
 ) -> Result<()>
 where
-    C: FnMut(u64, u64) -> CallbackReturn,
+    C: FnMut(u64, u64) -> R,
+    R: Into<CallbackReturn>,
 {
+    let mut callback = |sent, total| callback(sent, total).into();
     let mut callback: &mut dyn FnMut(u64, u64) -> CallbackReturn = &mut callback;
     let callback = &mut callback as *mut _ as *mut libc::c_void as *const _;
 
@@ -250,7 +612,7 @@ pub(crate) fn get_file_to_handler<H>(
     mtpdev: &MtpDevice,
     file: impl AsObjectId,
     mut handler: H,
-) -> Result<()>
+) -> Result<TransferOutcome>
 where
     H: FnMut(&[u8]) -> HandlerReturn,
 {
@@ -275,13 +637,14 @@ where
     } else {
         if handler_return.is_cancel() {
             let _ = mtpdev.latest_error();
+            Ok(TransferOutcome::Cancelled)
+        } else {
+            Ok(TransferOutcome::Completed)
         }
-
-        Ok(())
     }
 }
 
-pub(crate) fn get_file_to_handler_with_callback<H, C>(
+pub(crate) fn get_file_to_handler_with_callback<H, C, R>(
     mtpdev: &MtpDevice,
     file: impl AsObjectId,
     mut handler: H,
@@ -289,13 +652,15 @@ pub(crate) fn get_file_to_handler_with_callback<H, C>(
 ) -> Result<()>
 where
     H: FnMut(&[u8]) -> HandlerReturn,
-    C: FnMut(u64, u64) -> CallbackReturn,
+    C: FnMut(u64, u64) -> R,
+    R: Into<CallbackReturn>,
 {
     let handler: &mut dyn FnMut(&[u8]) -> HandlerReturn = &mut handler;
     let mut handler_return = HandlerReturn::Ok(0);
 
     let private = &mut (&mut handler_return, handler) as *mut _ as *mut libc::c_void;
 
+    let mut callback = |sent, total| callback(sent, total).into();
     let mut callback: &mut dyn FnMut(u64, u64) -> CallbackReturn = &mut callback;
     let callback = &mut callback as *mut _ as *mut libc::c_void as *const _;
 
@@ -327,12 +692,61 @@ pub(crate) fn send_file_from_path<'a>(
     path: impl AsRef<Path>,
     parent: Parent,
     metadata: FileMetadata<'_>,
+) -> Result<File<'a>> {
+    metadata.validate_for_path(path.as_ref())?;
+    check_storage_capacity(mtpdev, storage_id, metadata.file_size)?;
+    send_file_from_path_unchecked(mtpdev, storage_id, path, parent, metadata)
+}
+
+/// Picks the filetype to send an object of type `requested`, if the device doesn't support it
+/// falls back to the generic type of its category (`UndefAudio` or `UndefVideo`) or to `Unknown`,
+/// whichever is supported first. If none is supported `requested` is kept.
+fn supported_filetype_for(mtpdev: &MtpDevice, requested: &Filetype) -> Result<Filetype> {
+    let generic = match requested.mime_type() {
+        Some(mime) if mime.starts_with("audio/") => Some(Filetype::UndefAudio),
+        Some(mime) if mime.starts_with("video/") => Some(Filetype::UndefVideo),
+        _ => None,
+    };
+
+    let candidates = std::iter::once(requested.clone())
+        .chain(generic)
+        .chain(std::iter::once(Filetype::Unknown));
+
+    for candidate in candidates {
+        if mtpdev.supports_filetype(&candidate)? {
+            return Ok(candidate);
+        }
+    }
+
+    Ok(requested.clone())
+}
+
+pub(crate) fn send_file_from_path_with_fallback<'a>(
+    mtpdev: &'a MtpDevice,
+    storage_id: u32,
+    path: impl AsRef<Path>,
+    parent: Parent,
+    mut metadata: FileMetadata<'_>,
+) -> Result<(File<'a>, Filetype)> {
+    let filetype = supported_filetype_for(mtpdev, &metadata.file_type)?;
+    metadata.file_type = filetype.clone();
+
+    let file = send_file_from_path(mtpdev, storage_id, path, parent, metadata)?;
+    Ok((file, filetype))
+}
+
+pub(crate) fn send_file_from_path_unchecked<'a>(
+    mtpdev: &'a MtpDevice,
+    storage_id: u32,
+    path: impl AsRef<Path>,
+    parent: Parent,
+    metadata: FileMetadata<'_>,
 ) -> Result<File<'a>> {
     let path = path.as_ref();
     let path = path_to_cvec!(path);
 
     let file_t = unsafe { ffi::LIBMTP_new_file_t() };
-    unsafe { fill_file_t!(metadata, parent.to_id(), storage_id, file_t) };
+    unsafe { fill_file_t!(metadata, parent.to_id(), storage_id, file_t)? };
 
     let res = unsafe {
         ffi::LIBMTP_Send_File_From_File(
@@ -345,7 +759,7 @@ pub(crate) fn send_file_from_path<'a>(
     };
 
     if res != 0 {
-        Err(mtpdev.latest_error().unwrap_or_default())
+        Err(failed_send(mtpdev, file_t))
     } else {
         Ok(File {
             inner: file_t,
@@ -354,7 +768,7 @@ pub(crate) fn send_file_from_path<'a>(
     }
 }
 
-pub(crate) fn send_file_from_path_with_callback<'a, C>(
+pub(crate) fn send_file_from_path_with_callback<'a, C, R>(
     mtpdev: &'a MtpDevice,
     storage_id: u32,
     path: impl AsRef<Path>,
@@ -363,14 +777,18 @@ pub(crate) fn send_file_from_path_with_callback<'a, C>(
     mut callback: C,
 ) -> Result<File<'a>>
 where
-    C: FnMut(u64, u64) -> CallbackReturn,
+    C: FnMut(u64, u64) -> R,
+    R: Into<CallbackReturn>,
 {
     let path = path.as_ref();
+    metadata.validate_for_path(path)?;
+    check_storage_capacity(mtpdev, storage_id, metadata.file_size)?;
     let path = path_to_cvec!(path);
 
     let file_t = unsafe { ffi::LIBMTP_new_file_t() };
-    unsafe { fill_file_t!(metadata, parent.to_id(), storage_id, file_t) };
+    unsafe { fill_file_t!(metadata, parent.to_id(), storage_id, file_t)? };
 
+    let mut callback = |sent, total| callback(sent, total).into();
     let mut callback: &mut dyn FnMut(u64, u64) -> CallbackReturn = &mut callback;
     let callback = &mut callback as *mut _ as *mut libc::c_void as *const _;
 
@@ -385,7 +803,7 @@ where
     };
 
     if res != 0 {
-        Err(mtpdev.latest_error().unwrap_or_default())
+        Err(failed_send(mtpdev, file_t))
     } else {
         Ok(File {
             inner: file_t,
@@ -394,6 +812,21 @@ where
     }
 }
 
+pub(crate) fn send_file_from_path_throttled<'a>(
+    mtpdev: &'a MtpDevice,
+    storage_id: u32,
+    path: impl AsRef<Path>,
+    parent: Parent,
+    metadata: FileMetadata<'_>,
+    max_bytes_per_sec: u64,
+) -> Result<File<'a>> {
+    let mut throttle = Throttle::new(max_bytes_per_sec);
+    send_file_from_path_with_callback(mtpdev, storage_id, path, parent, metadata, |sent, _| {
+        throttle.wait(sent);
+        CallbackReturn::Continue
+    })
+}
+
 #[cfg(unix)]
 pub(crate) fn send_file_from_descriptor<'a>(
     mtpdev: &'a MtpDevice,
@@ -402,8 +835,11 @@ pub(crate) fn send_file_from_descriptor<'a>(
     parent: Parent,
     metadata: FileMetadata<'_>,
 ) -> Result<File<'a>> {
+    metadata.validate()?;
+    check_storage_capacity(mtpdev, storage_id, metadata.file_size)?;
+
     let file_t = unsafe { ffi::LIBMTP_new_file_t() };
-    unsafe { fill_file_t!(metadata, parent.to_id(), storage_id, file_t) };
+    unsafe { fill_file_t!(metadata, parent.to_id(), storage_id, file_t)? };
 
     let res = unsafe {
         ffi::LIBMTP_Send_File_From_File_Descriptor(
@@ -416,7 +852,7 @@ pub(crate) fn send_file_from_descriptor<'a>(
     };
 
     if res != 0 {
-        Err(mtpdev.latest_error().unwrap_or_default())
+        Err(failed_send(mtpdev, file_t))
     } else {
         Ok(File {
             inner: file_t,
@@ -426,7 +862,7 @@ pub(crate) fn send_file_from_descriptor<'a>(
 }
 
 #[cfg(unix)]
-pub(crate) fn send_file_from_descriptor_with_callback<'a, C>(
+pub(crate) fn send_file_from_descriptor_with_callback<'a, C, R>(
     mtpdev: &'a MtpDevice,
     storage_id: u32,
     descriptor: impl AsRawFd,
@@ -435,11 +871,16 @@ pub(crate) fn send_file_from_descriptor_with_callback<'a, C>(
     mut callback: C,
 ) -> Result<File<'a>>
 where
-    C: FnMut(u64, u64) -> CallbackReturn,
+    C: FnMut(u64, u64) -> R,
+    R: Into<CallbackReturn>,
 {
+    metadata.validate()?;
+    check_storage_capacity(mtpdev, storage_id, metadata.file_size)?;
+
     let file_t = unsafe { ffi::LIBMTP_new_file_t() };
-    unsafe { fill_file_t!(metadata, parent.to_id(), storage_id, file_t) };
+    unsafe { fill_file_t!(metadata, parent.to_id(), storage_id, file_t)? };
 
+    let mut callback = |sent, total| callback(sent, total).into();
     let mut callback: &mut dyn FnMut(u64, u64) -> CallbackReturn = &mut callback;
     let callback = &mut callback as *mut _ as *mut libc::c_void as *const _;
 
@@ -454,7 +895,7 @@ where
     };
 
     if res != 0 {
-        Err(mtpdev.latest_error().unwrap_or_default())
+        Err(failed_send(mtpdev, file_t))
     } else {
         Ok(File {
             inner: file_t,
@@ -473,13 +914,16 @@ pub(crate) fn send_file_from_handler<'a, H>(
 where
     H: FnMut(&mut [u8]) -> HandlerReturn,
 {
+    metadata.validate()?;
+    check_storage_capacity(mtpdev, storage_id, metadata.file_size)?;
+
     let handler: &mut dyn FnMut(&mut [u8]) -> HandlerReturn = &mut handler;
     let mut handler_return = HandlerReturn::Ok(0);
 
     let private = &mut (&mut handler_return, handler) as *mut _ as *mut libc::c_void;
 
     let file_t = unsafe { ffi::LIBMTP_new_file_t() };
-    unsafe { fill_file_t!(metadata, parent.to_id(), storage_id, file_t) };
+    unsafe { fill_file_t!(metadata, parent.to_id(), storage_id, file_t)? };
 
     let res = unsafe {
         ffi::LIBMTP_Send_File_From_Handler(
@@ -493,7 +937,7 @@ where
     };
 
     if res != 0 && handler_return.is_error() {
-        Err(mtpdev.latest_error().unwrap_or_default())
+        Err(failed_send(mtpdev, file_t))
     } else {
         if handler_return.is_cancel() {
             let _ = mtpdev.latest_error();
@@ -506,7 +950,41 @@ where
     }
 }
 
-pub(crate) fn send_file_from_handler_with_callback<'a, H, C>(
+pub(crate) fn send_file_from_handler_counted<'a, H>(
+    mtpdev: &'a MtpDevice,
+    storage_id: u32,
+    parent: Parent,
+    metadata: FileMetadata<'_>,
+    mut handler: H,
+    strict: bool,
+) -> Result<(File<'a>, u64)>
+where
+    H: FnMut(&mut [u8]) -> HandlerReturn,
+{
+    let expected = metadata.file_size;
+    let mut written = 0;
+
+    let file = send_file_from_handler(mtpdev, storage_id, parent, metadata, |data| {
+        let ret = handler(data);
+        if let HandlerReturn::Ok(len) = ret {
+            written += len as u64;
+        }
+
+        ret
+    })?;
+
+    if strict && written != expected {
+        if mtpdev.remove_failed_sends() {
+            let _ = file.delete();
+        }
+
+        Err(Error::SizeMismatch { expected, written })
+    } else {
+        Ok((file, written))
+    }
+}
+
+pub(crate) fn send_file_from_handler_with_callback<'a, H, C, R>(
     mtpdev: &'a MtpDevice,
     storage_id: u32,
     parent: Parent,
@@ -516,16 +994,21 @@ pub(crate) fn send_file_from_handler_with_callback<'a, H, C>(
 ) -> Result<File<'a>>
 where
     H: FnMut(&mut [u8]) -> HandlerReturn,
-    C: FnMut(u64, u64) -> CallbackReturn,
+    C: FnMut(u64, u64) -> R,
+    R: Into<CallbackReturn>,
 {
+    metadata.validate()?;
+    check_storage_capacity(mtpdev, storage_id, metadata.file_size)?;
+
     let handler: &mut dyn FnMut(&mut [u8]) -> HandlerReturn = &mut handler;
     let mut handler_return = HandlerReturn::Ok(0);
 
     let private = &mut (&mut handler_return, handler) as *mut _ as *mut libc::c_void;
 
     let file_t = unsafe { ffi::LIBMTP_new_file_t() };
-    unsafe { fill_file_t!(metadata, parent.to_id(), storage_id, file_t) };
+    unsafe { fill_file_t!(metadata, parent.to_id(), storage_id, file_t)? };
 
+    let mut callback = |sent, total| callback(sent, total).into();
     let mut callback: &mut dyn FnMut(u64, u64) -> CallbackReturn = &mut callback;
     let callback = &mut callback as *mut _ as *mut libc::c_void as *const _;
 
@@ -541,7 +1024,7 @@ where
     };
 
     if res != 0 && handler_return.is_error() {
-        Err(mtpdev.latest_error().unwrap_or_default())
+        Err(failed_send(mtpdev, file_t))
     } else {
         if handler_return.is_cancel() {
             let _ = mtpdev.latest_error();
@@ -553,3 +1036,133 @@ where
         })
     }
 }
+
+pub(crate) fn send_large_file<'a>(
+    mtpdev: &'a MtpDevice,
+    storage_id: u32,
+    path: impl AsRef<Path>,
+    parent: Parent,
+    metadata: FileMetadata<'_>,
+    buf_size: usize,
+) -> Result<File<'a>> {
+    let path = path.as_ref();
+    metadata.validate_for_path(path)?;
+
+    let mut local = std::fs::File::open(path)?;
+
+    let buf_size = buf_size.max(1);
+    let handler = |data: &mut [u8]| {
+        let len = data.len().min(buf_size);
+
+        match local.read(&mut data[..len]) {
+            Ok(read) => HandlerReturn::Ok(read as u32),
+            Err(_) => HandlerReturn::Error,
+        }
+    };
+
+    send_file_from_handler(mtpdev, storage_id, parent, metadata, handler)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(file_name: &str) -> FileMetadata<'_> {
+        FileMetadata {
+            file_size: 3,
+            file_name,
+            file_type: Filetype::Text,
+            modification_date: Utc.timestamp_opt(1_600_000_000, 0).unwrap(),
+        }
+    }
+
+    /// Allocates an empty file like `LIBMTP_new_file_t` does, so `libmtp` can destroy it.
+    unsafe fn new_file_t() -> *mut ffi::LIBMTP_file_t {
+        libc::calloc(1, std::mem::size_of::<ffi::LIBMTP_file_t>()) as *mut _
+    }
+
+    #[test]
+    fn fill_file_t_rejects_nul_bytes() {
+        let res: Result<()> = unsafe {
+            let file_t = new_file_t();
+            fill_file_t!(metadata("bad\0name"), 0, 0, file_t)
+        };
+
+        assert!(matches!(res, Err(Error::InvalidString { .. })));
+    }
+
+    #[test]
+    fn fill_file_t_copies_metadata() {
+        unsafe {
+            let file_t = new_file_t();
+            fill_file_t!(metadata("notes.txt"), 12, 65537, file_t).unwrap();
+
+            assert_eq!(CStr::from_ptr((*file_t).filename).to_str(), Ok("notes.txt"));
+            assert_eq!((*file_t).parent_id, 12);
+            assert_eq!((*file_t).storage_id, 65537);
+            assert_eq!((*file_t).filesize, 3);
+            assert_eq!((*file_t).modificationdate, 1_600_000_000);
+
+            ffi::LIBMTP_destroy_file_t(file_t);
+        }
+    }
+
+    #[test]
+    fn local_file_names_are_sanitized() {
+        let names = local_file_names(vec!["song.mp3", "a/b.txt", "c\\d.txt"]).unwrap();
+        assert_eq!(names, ["song.mp3", "a_b.txt", "c_d.txt"]);
+    }
+
+    #[test]
+    fn invalid_local_file_names_are_rejected() {
+        for name in ["", " ", ".", ".."] {
+            let res = local_file_names(vec![name]);
+            assert!(matches!(res, Err(Error::InvalidFileName { .. })));
+        }
+    }
+
+    #[test]
+    fn duplicated_local_file_names_are_rejected() {
+        let res = local_file_names(vec!["a.txt", "b.txt", "a.txt"]);
+        assert!(matches!(res, Err(Error::DuplicateFileName { name }) if name == "a.txt"));
+
+        let res = local_file_names(vec!["a/b", "a\\b"]);
+        assert!(matches!(res, Err(Error::DuplicateFileName { name }) if name == "a_b"));
+    }
+
+    #[test]
+    #[ignore = "needs a connected MTP device with at least three files at the root"]
+    fn gets_three_files_to_dir() {
+        use crate::device::raw::detect_raw_devices;
+        use crate::device::StorageSort;
+
+        let raw = detect_raw_devices().unwrap().remove(0);
+        let mut device = raw.try_open_uncached().unwrap();
+        device.update_storage(StorageSort::NotSorted).unwrap();
+
+        let pool = device.storage_pool();
+        let files: Vec<_> = pool
+            .files_and_folders(Parent::Root)
+            .into_iter()
+            .filter(|file| file.ftype() != Filetype::Folder)
+            .take(3)
+            .collect();
+        assert_eq!(files.len(), 3);
+
+        let dir = std::env::temp_dir().join(format!("libmtp-rs-dir-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let ids: Vec<_> = files.iter().map(File::id).collect();
+        let got = pool
+            .get_files_to_dir(&ids, &dir, |_, _, _, _| true)
+            .unwrap();
+        assert_eq!(got, 3);
+
+        for file in &files {
+            let local = std::fs::metadata(dir.join(file.name().as_ref())).unwrap();
+            assert_eq!(local.len(), file.size());
+        }
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+}
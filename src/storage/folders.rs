@@ -7,8 +7,10 @@ use std::fmt::{self, Debug};
 use libmtp_sys as ffi;
 
 use crate::device::MtpDevice;
+use crate::error::Error;
+use crate::object::filetypes::Filetype;
 use crate::object::Object;
-use crate::storage::Parent;
+use crate::storage::{files_and_folders, Parent};
 use crate::Result;
 
 pub struct Folder<'a> {
@@ -64,10 +66,10 @@ impl<'a> Folder<'a> {
         unsafe { (*self.inner).parent_id }
     }
 
-    pub fn name(&self) -> &str {
+    pub fn name(&self) -> Cow<'_, str> {
         unsafe {
             let cstr = CStr::from_ptr((*self.inner).name);
-            cstr.to_str().expect("Invalid UTF-8 on folder name")
+            cstr.to_string_lossy()
         }
     }
 
@@ -99,6 +101,37 @@ impl<'a> Folder<'a> {
         }
     }
 
+    /// Deletes this folder. This method shadows `Object::delete` on purpose, since deleting a
+    /// folder with contents may leave them orphaned or fail depending on the device:
+    ///
+    /// - If `recursive` is `false` and the folder has contents (files or folders), nothing is
+    ///   deleted and `Error::FolderNotEmpty` is returned.
+    /// - If `recursive` is `true` the contents are deleted first (depth first), then the folder
+    ///   itself, if some deletion fails the remaining objects are kept.
+    ///
+    /// In both cases this performs a request to list the contents of each folder. Use
+    /// `Object::delete(&folder)` if you really want the plain object deletion.
+    pub fn delete(&self, recursive: bool) -> Result<()> {
+        let storage_id = unsafe { (*self.inner).storage_id };
+        let parent = Parent::Folder(self.id());
+
+        if !recursive {
+            if !files_and_folders(self.owner, storage_id, parent).is_empty() {
+                return Err(Error::FolderNotEmpty { id: self.id() });
+            }
+        } else {
+            delete_contents(self.owner, storage_id, self.id())?;
+        }
+
+        Object::delete(self)
+    }
+
+    /// Same as [`delete`](#method.delete), but consumes the handle so it can't be used with an
+    /// invalid id afterwards. Note that the handle is dropped even if the deletion fails.
+    pub fn delete_consume(self, recursive: bool) -> Result<()> {
+        self.delete(recursive)
+    }
+
     pub fn find(&self, folder_id: u32) -> Option<Folder<'a>> {
         let folder = unsafe { ffi::LIBMTP_Find_Folder(self.inner, folder_id) };
 
@@ -114,7 +147,7 @@ impl<'a> Folder<'a> {
     }
 
     pub fn rename(&mut self, new_name: &str) -> Result<()> {
-        let new_name = CString::new(new_name).expect("Nul byte");
+        let new_name = CString::new(new_name)?;
 
         let res =
             unsafe { ffi::LIBMTP_Set_Folder_Name(self.owner.inner, self.inner, new_name.as_ptr()) };
@@ -127,6 +160,52 @@ impl<'a> Folder<'a> {
     }
 }
 
+/// Maximum length of an MTP string (in UTF-16 code units), excluding the terminating null.
+const MAX_NAME_LEN: usize = 254;
+
+/// Applies common restrictions of MTP devices to a folder name, so it's less likely that the
+/// device truncates or rejects it when creating a folder: path separators (`/` and `\\`), nul and
+/// control characters are replaced with `_`, surrounding whitespace is removed and the name is
+/// capped to the maximum length of an MTP string (254 UTF-16 code units).
+///
+/// Note that devices may still apply their own restrictions, that's why
+/// [`Storage::create_folder`](../struct.Storage.html#method.create_folder) returns the final name.
+pub fn sanitize_folder_name(name: &str) -> String {
+    let mut len = 0;
+
+    name.trim()
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .take_while(|c| {
+            len += c.len_utf16();
+            len <= MAX_NAME_LEN
+        })
+        .collect()
+}
+
+/// Checks whether a folder name is non-empty and wouldn't be changed by
+/// [`sanitize_folder_name`](fn.sanitize_folder_name.html).
+pub fn is_valid_folder_name(name: &str) -> bool {
+    !name.is_empty() && sanitize_folder_name(name) == name
+}
+
+/// Deletes (recursively) the contents of the folder with the given id.
+fn delete_contents(mtpdev: &MtpDevice, storage_id: u32, folder_id: u32) -> Result<()> {
+    for file in files_and_folders(mtpdev, storage_id, Parent::Folder(folder_id)) {
+        if let Filetype::Folder = file.ftype() {
+            delete_contents(mtpdev, storage_id, file.id())?;
+        }
+
+        file.delete()?;
+    }
+
+    Ok(())
+}
+
 pub(crate) fn get_folder_list(mtpdev: &MtpDevice) -> Option<Folder<'_>> {
     let folder = unsafe { ffi::LIBMTP_Get_Folder_List(mtpdev.inner) };
 
@@ -141,6 +220,143 @@ pub(crate) fn get_folder_list(mtpdev: &MtpDevice) -> Option<Folder<'_>> {
     }
 }
 
+/// Owned node of a folder tree (see `Storage::folder_tree`), unlike `Folder` it isn't tied to
+/// the device lifetime, so it can be kept around.
+#[derive(Debug, Clone)]
+pub struct FolderNode {
+    pub id: u32,
+    pub name: String,
+    pub children: Vec<FolderNode>,
+}
+
+pub(crate) fn folder_tree(mtpdev: &MtpDevice, storage_id: u32) -> Option<FolderNode> {
+    let folder = unsafe { ffi::LIBMTP_Get_Folder_List_For_Storage(mtpdev.inner, storage_id) };
+
+    if folder.is_null() {
+        None
+    } else {
+        let children = unsafe { nodes_from_level(folder) };
+        unsafe { ffi::LIBMTP_destroy_folder_t(folder) };
+
+        Some(FolderNode {
+            id: 0,
+            name: String::new(),
+            children,
+        })
+    }
+}
+
+/// Builds the owned nodes of `folder` and its siblings (recursively with their children).
+unsafe fn nodes_from_level(mut folder: *mut ffi::LIBMTP_folder_t) -> Vec<FolderNode> {
+    let mut nodes = Vec::new();
+
+    while !folder.is_null() {
+        let name = if (*folder).name.is_null() {
+            String::new()
+        } else {
+            CStr::from_ptr((*folder).name)
+                .to_string_lossy()
+                .into_owned()
+        };
+
+        nodes.push(FolderNode {
+            id: (*folder).folder_id,
+            name,
+            children: nodes_from_level((*folder).child),
+        });
+
+        folder = (*folder).sibling;
+    }
+
+    nodes
+}
+
+/// Looks for a folder named `name` among `folder` and its siblings.
+unsafe fn find_in_level(
+    mut folder: *mut ffi::LIBMTP_folder_t,
+    name: &str,
+) -> Option<*mut ffi::LIBMTP_folder_t> {
+    while !folder.is_null() {
+        if !(*folder).name.is_null() && CStr::from_ptr((*folder).name).to_bytes() == name.as_bytes()
+        {
+            return Some(folder);
+        }
+
+        folder = (*folder).sibling;
+    }
+
+    None
+}
+
+/// Owned folder tree of all the storages (as returned by `LIBMTP_Get_Folder_List`), unlike
+/// `Folder` this isn't tied to the device lifetime, so it can be cached.
+pub(crate) struct FolderTree {
+    root: *mut ffi::LIBMTP_folder_t,
+}
+
+impl Drop for FolderTree {
+    fn drop(&mut self) {
+        if !self.root.is_null() {
+            unsafe {
+                ffi::LIBMTP_destroy_folder_t(self.root);
+            }
+        }
+    }
+}
+
+impl FolderTree {
+    pub(crate) fn fetch(mtpdev: &MtpDevice) -> Self {
+        let root = unsafe { ffi::LIBMTP_Get_Folder_List(mtpdev.inner) };
+        FolderTree { root }
+    }
+
+    /// Resolves a folder path (components separated by `/`, starting from the root), an empty
+    /// path resolves to the root.
+    pub(crate) fn find_by_path(&self, path: &str) -> Option<Parent> {
+        let mut level = self.root;
+        let mut parent = Parent::Root;
+
+        for component in path.split('/').filter(|c| !c.is_empty()) {
+            unsafe {
+                let folder = find_in_level(level, component)?;
+                parent = Parent::Folder((*folder).folder_id);
+                level = (*folder).child;
+            }
+        }
+
+        Some(parent)
+    }
+
+    /// Removes the folder with the given id (and its subfolders) from this tree, returns whether
+    /// it was found.
+    pub(crate) fn remove(&mut self, id: u32) -> bool {
+        unsafe { remove_from_level(&mut self.root, id) }
+    }
+}
+
+/// Unlinks and destroys the folder with the given id, searching (recursively) from the level
+/// pointed by `link`.
+unsafe fn remove_from_level(mut link: *mut *mut ffi::LIBMTP_folder_t, id: u32) -> bool {
+    while !(*link).is_null() {
+        let folder = *link;
+
+        if (*folder).folder_id == id {
+            *link = (*folder).sibling;
+            (*folder).sibling = std::ptr::null_mut();
+            ffi::LIBMTP_destroy_folder_t(folder);
+            return true;
+        }
+
+        if remove_from_level(&mut (*folder).child, id) {
+            return true;
+        }
+
+        link = &mut (*folder).sibling;
+    }
+
+    false
+}
+
 pub(crate) fn get_folder_list_storage(mtpdev: &MtpDevice, storage_id: u32) -> Option<Folder<'_>> {
     let folder = unsafe { ffi::LIBMTP_Get_Folder_List_For_Storage(mtpdev.inner, storage_id) };
 
@@ -161,7 +377,7 @@ pub(crate) fn create_folder<'a>(
     parent: Parent,
     storage_id: u32,
 ) -> Result<(u32, Cow<'a, str>)> {
-    let name_cstr = CString::new(name).expect("Nul byte");
+    let name_cstr = CString::new(name)?;
     let parent = parent.faf_id();
 
     let name_in_c = unsafe { libc::strdup(name_cstr.as_ptr()) };
@@ -0,0 +1,290 @@
+//! Contains relevant items to handle playlist objects in the device.
+
+use std::fmt::{self, Debug};
+
+use libmtp_sys as ffi;
+
+use crate::device::MtpDevice;
+use crate::error::Error;
+use crate::object::{AsObjectId, Object};
+use crate::storage::files::{self, File};
+use crate::storage::Parent;
+use crate::util::{optional_str, optional_strdup, u32_slice_to_c_array};
+use crate::Result;
+
+/// Abstraction of a playlist object, it implements `Object`. A playlist is an ordered list of
+/// track ids, note that the list of tracks is the one gathered when this playlist was retrieved.
+pub struct Playlist<'a> {
+    pub(crate) inner: *mut ffi::LIBMTP_playlist_t,
+    pub(crate) owner: &'a MtpDevice,
+}
+
+impl Drop for Playlist<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::LIBMTP_destroy_playlist_t(self.inner);
+        }
+    }
+}
+
+impl Object for Playlist<'_> {
+    fn id(&self) -> u32 {
+        unsafe { (*self.inner).playlist_id }
+    }
+
+    fn device(&self) -> &MtpDevice {
+        self.owner
+    }
+}
+
+impl Object for &Playlist<'_> {
+    fn id(&self) -> u32 {
+        unsafe { (*self.inner).playlist_id }
+    }
+
+    fn device(&self) -> &MtpDevice {
+        self.owner
+    }
+}
+
+impl Debug for Playlist<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Playlist")
+            .field("id", &self.id())
+            .field("name", &self.name())
+            .field("tracks", &self.tracks())
+            .finish()
+    }
+}
+
+impl<'a> Playlist<'a> {
+    /// Returns the name of this playlist, if any.
+    pub fn name(&self) -> Option<&str> {
+        unsafe { optional_str((*self.inner).name) }
+    }
+
+    /// Returns the ids of the tracks in this playlist, in order.
+    pub fn tracks(&self) -> &[u32] {
+        unsafe {
+            let tracks = (*self.inner).tracks;
+            let len = (*self.inner).no_tracks as usize;
+
+            if tracks.is_null() || len == 0 {
+                &[]
+            } else {
+                std::slice::from_raw_parts(tracks, len)
+            }
+        }
+    }
+
+    /// Reorders the tracks of this playlist and updates the playlist in the device. The new
+    /// order must be a permutation of the current tracks (same ids, same number of times),
+    /// otherwise `Error::InvalidOrder` is returned and nothing is changed.
+    pub fn reorder(&mut self, new_order: &[u32]) -> Result<()> {
+        check_permutation(self.tracks(), new_order)?;
+        self.update(new_order)
+    }
+
+    /// Inserts a track at the given position of this playlist and updates the playlist in the
+    /// device. If `index` is greater than the number of tracks, the track is appended.
+    pub fn insert_at(&mut self, index: usize, track: impl AsObjectId) -> Result<()> {
+        let tracks = inserted_at(self.tracks(), index, track.as_id());
+        self.update(&tracks)
+    }
+
+    /// Retrieves the files of the tracks referenced by this playlist. If `skip_missing` is `true`
+    /// the tracks that can't be retrieved (e.g. deleted) are skipped, otherwise the first error
+    /// is returned.
+    pub fn referenced_files(&self, skip_missing: bool) -> Result<Vec<File<'a>>> {
+        files::files_from_ids(self.owner, self.tracks(), skip_missing)
+    }
+
+    /// Replaces the tracks of this playlist and updates it in the device, on failure the
+    /// previous list of tracks is kept.
+    pub fn update(&mut self, tracks: &[u32]) -> Result<()> {
+        unsafe {
+            let new_tracks = u32_slice_to_c_array(tracks);
+
+            let old_tracks = (*self.inner).tracks;
+            let old_len = (*self.inner).no_tracks;
+
+            (*self.inner).tracks = new_tracks;
+            (*self.inner).no_tracks = tracks.len() as u32;
+
+            let res = ffi::LIBMTP_Update_Playlist(self.owner.inner, self.inner);
+
+            if res != 0 {
+                (*self.inner).tracks = old_tracks;
+                (*self.inner).no_tracks = old_len;
+                libc::free(new_tracks as *mut _);
+
+                Err(self.owner.latest_error().unwrap_or_default())
+            } else {
+                libc::free(old_tracks as *mut _);
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Checks that `new_order` is a permutation of `current` (same ids, same number of times).
+fn check_permutation(current: &[u32], new_order: &[u32]) -> Result<()> {
+    let mut current = current.to_vec();
+    let mut requested = new_order.to_vec();
+
+    current.sort_unstable();
+    requested.sort_unstable();
+
+    if current != requested {
+        Err(Error::InvalidOrder)
+    } else {
+        Ok(())
+    }
+}
+
+/// Returns the tracks with `track` inserted at `index`, or appended if `index` is past the end.
+fn inserted_at(tracks: &[u32], index: usize, track: u32) -> Vec<u32> {
+    let mut tracks = tracks.to_vec();
+    let index = index.min(tracks.len());
+
+    tracks.insert(index, track);
+    tracks
+}
+
+pub(crate) fn get_playlist(mtpdev: &MtpDevice, playlist: impl AsObjectId) -> Result<Playlist<'_>> {
+    let playlist = unsafe { ffi::LIBMTP_Get_Playlist(mtpdev.inner, playlist.as_id()) };
+
+    if playlist.is_null() {
+        Err(mtpdev.latest_error().unwrap_or_default())
+    } else {
+        Ok(Playlist {
+            inner: playlist,
+            owner: mtpdev,
+        })
+    }
+}
+
+pub(crate) fn playlist_list(mtpdev: &MtpDevice) -> Vec<Playlist<'_>> {
+    let mut head = unsafe { ffi::LIBMTP_Get_Playlist_List(mtpdev.inner) };
+
+    let mut playlists = Vec::new();
+    while !head.is_null() {
+        playlists.push(Playlist {
+            inner: head,
+            owner: mtpdev,
+        });
+
+        head = unsafe { (*head).next };
+    }
+
+    playlists
+}
+
+pub(crate) fn create_playlist<'a>(
+    mtpdev: &'a MtpDevice,
+    storage_id: u32,
+    parent: Parent,
+    name: &str,
+    tracks: &[u32],
+) -> Result<Playlist<'a>> {
+    let playlist = unsafe { ffi::LIBMTP_new_playlist_t() };
+    let playlist = Playlist {
+        inner: playlist,
+        owner: mtpdev,
+    };
+
+    unsafe {
+        let inner = playlist.inner;
+
+        (*inner).parent_id = parent.to_id();
+        (*inner).storage_id = storage_id;
+        (*inner).name = optional_strdup(Some(name))?;
+
+        if !tracks.is_empty() {
+            (*inner).tracks = u32_slice_to_c_array(tracks);
+            (*inner).no_tracks = tracks.len() as u32;
+        }
+    }
+
+    let res = unsafe { ffi::LIBMTP_Create_New_Playlist(mtpdev.inner, playlist.inner) };
+
+    if res != 0 {
+        Err(mtpdev.latest_error().unwrap_or_default())
+    } else {
+        Ok(playlist)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn permutations_are_accepted() {
+        assert!(check_permutation(&[], &[]).is_ok());
+        assert!(check_permutation(&[1, 2, 3], &[3, 1, 2]).is_ok());
+        assert!(check_permutation(&[1, 1, 2], &[1, 2, 1]).is_ok());
+    }
+
+    #[test]
+    fn other_orders_are_rejected() {
+        let invalid = |new_order: &[u32]| {
+            matches!(
+                check_permutation(&[1, 2, 3], new_order),
+                Err(Error::InvalidOrder)
+            )
+        };
+
+        assert!(invalid(&[1, 1, 2]), "duplicated id");
+        assert!(invalid(&[1, 2]), "missing id");
+        assert!(invalid(&[1, 2, 3, 4]), "extra id");
+        assert!(invalid(&[1, 2, 4]), "unknown id");
+    }
+
+    #[test]
+    fn inserts_at_index() {
+        assert_eq!(inserted_at(&[1, 2, 3], 0, 9), [9, 1, 2, 3]);
+        assert_eq!(inserted_at(&[1, 2, 3], 1, 9), [1, 9, 2, 3]);
+        assert_eq!(inserted_at(&[1, 2, 3], 3, 9), [1, 2, 3, 9]);
+    }
+
+    #[test]
+    fn insert_past_the_end_appends() {
+        assert_eq!(inserted_at(&[1, 2, 3], 10, 9), [1, 2, 3, 9]);
+        assert_eq!(inserted_at(&[], 5, 9), [9]);
+    }
+
+    #[test]
+    #[ignore = "needs a connected MTP device with at least three audio files"]
+    fn order_survives_a_round_trip() {
+        use crate::device::raw::detect_raw_devices;
+        use crate::device::StorageSort;
+
+        let raw = detect_raw_devices().unwrap().remove(0);
+        let mut device = raw.try_open_uncached().unwrap();
+        device.update_storage(StorageSort::NotSorted).unwrap();
+
+        let pool = device.storage_pool();
+        let tracks: Vec<_> = pool
+            .walk(Parent::Root)
+            .filter(|file| file.ftype().is_audio())
+            .take(3)
+            .map(|file| file.id())
+            .collect();
+        assert_eq!(tracks.len(), 3);
+
+        let mut playlist = pool
+            .create_playlist("libmtp-rs test playlist", &tracks[..2], Parent::Root)
+            .unwrap();
+
+        playlist.reorder(&[tracks[1], tracks[0]]).unwrap();
+        let read = pool.get_playlist(playlist.id()).unwrap();
+        assert_eq!(read.tracks(), [tracks[1], tracks[0]]);
+
+        playlist.insert_at(1, tracks[2]).unwrap();
+        let read = pool.get_playlist(playlist.id()).unwrap();
+        assert_eq!(read.tracks(), [tracks[1], tracks[2], tracks[0]]);
+
+        playlist.delete().unwrap();
+    }
+}
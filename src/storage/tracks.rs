@@ -0,0 +1,572 @@
+//! Contains relevant items to handle track objects in the device.
+
+use libmtp_sys as ffi;
+use num_traits::ToPrimitive;
+use std::fmt::{self, Debug};
+use std::path::Path;
+
+#[cfg(unix)]
+use std::os::unix::io::AsRawFd;
+
+use crate::device::MtpDevice;
+use crate::error::Error;
+use crate::object::{AsObjectId, Object};
+use crate::storage::files::{self, FileMetadata};
+use crate::storage::Parent;
+use crate::util::{data_get_func_handler, progress_func_handler, CallbackReturn, HandlerReturn};
+use crate::util::{optional_str, optional_strdup};
+use crate::Result;
+
+/// Abstraction of a track object, it implements `Object`. A track is a file with audio (or
+/// video) metadata like its title, artist, album, etc.
+pub struct Track<'a> {
+    pub(crate) inner: *mut ffi::LIBMTP_track_t,
+    pub(crate) owner: &'a MtpDevice,
+}
+
+impl Drop for Track<'_> {
+    fn drop(&mut self) {
+        unsafe {
+            ffi::LIBMTP_destroy_track_t(self.inner);
+        }
+    }
+}
+
+impl Object for Track<'_> {
+    fn id(&self) -> u32 {
+        unsafe { (*self.inner).item_id }
+    }
+
+    fn device(&self) -> &MtpDevice {
+        self.owner
+    }
+}
+
+impl Object for &Track<'_> {
+    fn id(&self) -> u32 {
+        unsafe { (*self.inner).item_id }
+    }
+
+    fn device(&self) -> &MtpDevice {
+        self.owner
+    }
+}
+
+impl Debug for Track<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Track")
+            .field("id", &self.id())
+            .field("title", &self.title())
+            .field("artist", &self.artist())
+            .field("album", &self.album())
+            .field("genre", &self.genre())
+            .field("duration", &self.duration())
+            .field("filesize", &self.filesize())
+            .finish()
+    }
+}
+
+impl<'a> Track<'a> {
+    /// Retrieves the metadata of the track with the given id, this is useful to rebuild a `Track`
+    /// from an id you stored before (e.g. across reconnections), note that ids are unique across
+    /// all the device. Returns `Error::ObjectNotFound` if there isn't a track with such id.
+    pub fn from_id(mtpdev: &'a MtpDevice, id: impl AsObjectId) -> Result<Track<'a>> {
+        let id = id.as_id();
+        let track = unsafe { ffi::LIBMTP_Get_Trackmetadata(mtpdev.inner, id) };
+
+        if track.is_null() {
+            Err(mtpdev
+                .latest_error()
+                .unwrap_or(Error::ObjectNotFound { id }))
+        } else {
+            Ok(Track {
+                inner: track,
+                owner: mtpdev,
+            })
+        }
+    }
+}
+
+impl Track<'_> {
+    /// Returns the title of this track, if any.
+    pub fn title(&self) -> Option<&str> {
+        unsafe { optional_str((*self.inner).title) }
+    }
+
+    /// Returns the artist of this track, if any.
+    pub fn artist(&self) -> Option<&str> {
+        unsafe { optional_str((*self.inner).artist) }
+    }
+
+    /// Returns the album of this track, if any.
+    pub fn album(&self) -> Option<&str> {
+        unsafe { optional_str((*self.inner).album) }
+    }
+
+    /// Returns the genre of this track, if any.
+    pub fn genre(&self) -> Option<&str> {
+        unsafe { optional_str((*self.inner).genre) }
+    }
+
+    /// Returns the composer of this track, if any.
+    pub fn composer(&self) -> Option<&str> {
+        unsafe { optional_str((*self.inner).composer) }
+    }
+
+    /// Returns the duration of this track in milliseconds.
+    pub fn duration(&self) -> u32 {
+        unsafe { (*self.inner).duration }
+    }
+
+    /// Returns the sample rate of the original file (in Hz).
+    pub fn samplerate(&self) -> u32 {
+        unsafe { (*self.inner).samplerate }
+    }
+
+    /// Returns the number of channels of this track, `0` means unknown.
+    pub fn nochannels(&self) -> u16 {
+        unsafe { (*self.inner).nochannels }
+    }
+
+    /// Returns the FourCC wave codec name of this track.
+    pub fn wavecodec(&self) -> u32 {
+        unsafe { (*self.inner).wavecodec }
+    }
+
+    /// Returns the (average) bitrate of this track.
+    pub fn bitrate(&self) -> u32 {
+        unsafe { (*self.inner).bitrate }
+    }
+
+    /// Returns the bitrate type of this track, `0` means unused, `1` constant, `2` variable
+    /// (VBR) and `3` free.
+    pub fn bitratetype(&self) -> u16 {
+        unsafe { (*self.inner).bitratetype }
+    }
+
+    /// Returns the user rating of this track, from `0` to `100`.
+    pub fn rating(&self) -> u16 {
+        unsafe { (*self.inner).rating }
+    }
+
+    /// Returns the number of times this track was used (played).
+    pub fn usecount(&self) -> u32 {
+        unsafe { (*self.inner).usecount }
+    }
+
+    /// Returns the size of the track file in bytes.
+    pub fn filesize(&self) -> u64 {
+        unsafe { (*self.inner).filesize }
+    }
+}
+
+/// Internal function to build the tracks from a linked list returned by libmtp.
+fn tracks_from_list(mtpdev: &MtpDevice, mut head: *mut ffi::LIBMTP_track_t) -> Vec<Track<'_>> {
+    let mut tracks = Vec::new();
+    while !head.is_null() {
+        tracks.push(Track {
+            inner: head,
+            owner: mtpdev,
+        });
+
+        head = unsafe { (*head).next };
+    }
+
+    tracks
+}
+
+pub(crate) fn track_listing(mtpdev: &MtpDevice, storage_id: u32) -> Vec<Track<'_>> {
+    let head = if storage_id == 0 {
+        unsafe { ffi::LIBMTP_Get_Tracklisting(mtpdev.inner) }
+    } else {
+        unsafe {
+            ffi::LIBMTP_Get_Tracklisting_With_Callback_For_Storage(
+                mtpdev.inner,
+                storage_id,
+                None,
+                std::ptr::null(),
+            )
+        }
+    };
+
+    tracks_from_list(mtpdev, head)
+}
+
+pub(crate) fn track_listing_with_callback<C, R>(
+    mtpdev: &MtpDevice,
+    storage_id: u32,
+    mut callback: C,
+) -> Vec<Track<'_>>
+where
+    C: FnMut(u64, u64) -> R,
+    R: Into<CallbackReturn>,
+{
+    let mut callback = |sent, total| callback(sent, total).into();
+    let mut callback: &mut dyn FnMut(u64, u64) -> CallbackReturn = &mut callback;
+    let callback = &mut callback as *mut _ as *mut libc::c_void as *const _;
+
+    let head = if storage_id == 0 {
+        unsafe {
+            ffi::LIBMTP_Get_Tracklisting_With_Callback(
+                mtpdev.inner,
+                Some(progress_func_handler),
+                callback,
+            )
+        }
+    } else {
+        unsafe {
+            ffi::LIBMTP_Get_Tracklisting_With_Callback_For_Storage(
+                mtpdev.inner,
+                storage_id,
+                Some(progress_func_handler),
+                callback,
+            )
+        }
+    };
+
+    tracks_from_list(mtpdev, head)
+}
+
+/// Convenience struct used as a parameter to send local tracks to an MTP device, besides the
+/// metadata of the file it contains the audio metadata, the optional strings are only sent
+/// when they're given.
+///
+/// ## Example
+/// ```no_run
+/// # use libmtp_rs::storage::files::FileMetadata;
+/// # use libmtp_rs::storage::tracks::TrackMetadata;
+/// # fn metadata(file: FileMetadata<'_>) -> TrackMetadata<'_> {
+/// TrackMetadata {
+///     title: Some("Song"),
+///     artist: Some("Artist"),
+///     duration: 215_000,
+///     ..TrackMetadata::new(file)
+/// }
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct TrackMetadata<'a> {
+    pub file: FileMetadata<'a>,
+    pub title: Option<&'a str>,
+    pub artist: Option<&'a str>,
+    pub composer: Option<&'a str>,
+    pub genre: Option<&'a str>,
+    pub album: Option<&'a str>,
+    pub date: Option<&'a str>,
+    pub tracknumber: u16,
+    pub duration: u32,
+    pub samplerate: u32,
+    pub nochannels: u16,
+    pub wavecodec: u32,
+    pub bitrate: u32,
+    pub bitratetype: u16,
+    pub rating: u16,
+    pub usecount: u32,
+}
+
+impl<'a> TrackMetadata<'a> {
+    /// Creates the metadata of a track without audio metadata, i.e. no strings and zeroed
+    /// numbers (unknown).
+    pub fn new(file: FileMetadata<'a>) -> Self {
+        TrackMetadata {
+            file,
+            title: None,
+            artist: None,
+            composer: None,
+            genre: None,
+            album: None,
+            date: None,
+            tracknumber: 0,
+            duration: 0,
+            samplerate: 0,
+            nochannels: 0,
+            wavecodec: 0,
+            bitrate: 0,
+            bitratetype: 0,
+            rating: 0,
+            usecount: 0,
+        }
+    }
+
+    /// Checks the strings of this metadata, see `FileMetadata::validate`.
+    fn validate(&self) -> Result<()> {
+        self.file.validate()?;
+
+        let strings = [
+            self.title,
+            self.artist,
+            self.composer,
+            self.genre,
+            self.album,
+            self.date,
+        ];
+
+        if strings.iter().flatten().any(|s| s.contains('\0')) {
+            Err(Error::InvalidMetadata {
+                reason: "track metadata contains a nul byte",
+            })
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Builds a new `LIBMTP_track_t` with the given metadata, the metadata must be validated first.
+unsafe fn new_track_t(
+    metadata: &TrackMetadata<'_>,
+    parent: Parent,
+    storage_id: u32,
+) -> Result<*mut ffi::LIBMTP_track_t> {
+    let track_t = ffi::LIBMTP_new_track_t();
+
+    let fill = || -> Result<()> {
+        (*track_t).parent_id = parent.to_id();
+        (*track_t).storage_id = storage_id;
+        (*track_t).filename = optional_strdup(Some(metadata.file.file_name))?;
+        (*track_t).filesize = metadata.file.file_size;
+        (*track_t).filetype = metadata
+            .file
+            .file_type
+            .to_u32()
+            .expect("Unexpected variant in Filetype");
+        (*track_t).modificationdate = metadata.file.modification_date.timestamp() as libc::time_t;
+
+        (*track_t).title = optional_strdup(metadata.title)?;
+        (*track_t).artist = optional_strdup(metadata.artist)?;
+        (*track_t).composer = optional_strdup(metadata.composer)?;
+        (*track_t).genre = optional_strdup(metadata.genre)?;
+        (*track_t).album = optional_strdup(metadata.album)?;
+        (*track_t).date = optional_strdup(metadata.date)?;
+
+        (*track_t).tracknumber = metadata.tracknumber;
+        (*track_t).duration = metadata.duration;
+        (*track_t).samplerate = metadata.samplerate;
+        (*track_t).nochannels = metadata.nochannels;
+        (*track_t).wavecodec = metadata.wavecodec;
+        (*track_t).bitrate = metadata.bitrate;
+        (*track_t).bitratetype = metadata.bitratetype;
+        (*track_t).rating = metadata.rating;
+        (*track_t).usecount = metadata.usecount;
+
+        Ok(())
+    };
+
+    match fill() {
+        Ok(()) => Ok(track_t),
+        Err(err) => {
+            ffi::LIBMTP_destroy_track_t(track_t);
+            Err(err)
+        }
+    }
+}
+
+/// Sends a track using `send` to perform the actual request (given the new `LIBMTP_track_t`),
+/// which returns whether the request failed, on failure behaves like the rest of sends, see
+/// `MtpDevice::set_remove_failed_sends`.
+fn send_track<'a>(
+    mtpdev: &'a MtpDevice,
+    storage_id: u32,
+    parent: Parent,
+    metadata: &TrackMetadata<'_>,
+    send: impl FnOnce(*mut ffi::LIBMTP_track_t) -> bool,
+) -> Result<Track<'a>> {
+    metadata.validate()?;
+    files::check_storage_capacity(mtpdev, storage_id, metadata.file.file_size)?;
+
+    let track_t = unsafe { new_track_t(metadata, parent, storage_id)? };
+
+    if send(track_t) {
+        let err = mtpdev.latest_error().unwrap_or_default();
+
+        unsafe {
+            let id = (*track_t).item_id;
+
+            if mtpdev.remove_failed_sends() && id != 0 {
+                ffi::LIBMTP_Delete_Object(mtpdev.inner, id);
+                let _ = mtpdev.latest_error();
+            }
+
+            ffi::LIBMTP_destroy_track_t(track_t);
+        }
+
+        Err(err)
+    } else {
+        Ok(Track {
+            inner: track_t,
+            owner: mtpdev,
+        })
+    }
+}
+
+pub(crate) fn send_track_from_path<'a>(
+    mtpdev: &'a MtpDevice,
+    storage_id: u32,
+    path: impl AsRef<Path>,
+    parent: Parent,
+    metadata: TrackMetadata<'_>,
+) -> Result<Track<'a>> {
+    let path = path.as_ref();
+    metadata.file.validate_for_path(path)?;
+    let path = path_to_cvec!(path);
+
+    send_track(mtpdev, storage_id, parent, &metadata, |track_t| unsafe {
+        ffi::LIBMTP_Send_Track_From_File(
+            mtpdev.inner,
+            path.as_ptr() as *const _,
+            track_t,
+            None,
+            std::ptr::null(),
+        ) != 0
+    })
+}
+
+pub(crate) fn send_track_from_path_with_callback<'a, C, R>(
+    mtpdev: &'a MtpDevice,
+    storage_id: u32,
+    path: impl AsRef<Path>,
+    parent: Parent,
+    metadata: TrackMetadata<'_>,
+    mut callback: C,
+) -> Result<Track<'a>>
+where
+    C: FnMut(u64, u64) -> R,
+    R: Into<CallbackReturn>,
+{
+    let path = path.as_ref();
+    metadata.file.validate_for_path(path)?;
+    let path = path_to_cvec!(path);
+
+    let mut callback = |sent, total| callback(sent, total).into();
+    let mut callback: &mut dyn FnMut(u64, u64) -> CallbackReturn = &mut callback;
+    let callback = &mut callback as *mut _ as *mut libc::c_void as *const _;
+
+    send_track(mtpdev, storage_id, parent, &metadata, |track_t| unsafe {
+        ffi::LIBMTP_Send_Track_From_File(
+            mtpdev.inner,
+            path.as_ptr() as *const _,
+            track_t,
+            Some(progress_func_handler),
+            callback,
+        ) != 0
+    })
+}
+
+#[cfg(unix)]
+pub(crate) fn send_track_from_descriptor<'a>(
+    mtpdev: &'a MtpDevice,
+    storage_id: u32,
+    descriptor: impl AsRawFd,
+    parent: Parent,
+    metadata: TrackMetadata<'_>,
+) -> Result<Track<'a>> {
+    send_track(mtpdev, storage_id, parent, &metadata, |track_t| unsafe {
+        ffi::LIBMTP_Send_Track_From_File_Descriptor(
+            mtpdev.inner,
+            descriptor.as_raw_fd(),
+            track_t,
+            None,
+            std::ptr::null(),
+        ) != 0
+    })
+}
+
+#[cfg(unix)]
+pub(crate) fn send_track_from_descriptor_with_callback<'a, C, R>(
+    mtpdev: &'a MtpDevice,
+    storage_id: u32,
+    descriptor: impl AsRawFd,
+    parent: Parent,
+    metadata: TrackMetadata<'_>,
+    mut callback: C,
+) -> Result<Track<'a>>
+where
+    C: FnMut(u64, u64) -> R,
+    R: Into<CallbackReturn>,
+{
+    let mut callback = |sent, total| callback(sent, total).into();
+    let mut callback: &mut dyn FnMut(u64, u64) -> CallbackReturn = &mut callback;
+    let callback = &mut callback as *mut _ as *mut libc::c_void as *const _;
+
+    send_track(mtpdev, storage_id, parent, &metadata, |track_t| unsafe {
+        ffi::LIBMTP_Send_Track_From_File_Descriptor(
+            mtpdev.inner,
+            descriptor.as_raw_fd(),
+            track_t,
+            Some(progress_func_handler),
+            callback,
+        ) != 0
+    })
+}
+
+pub(crate) fn send_track_from_handler<'a, H>(
+    mtpdev: &'a MtpDevice,
+    storage_id: u32,
+    parent: Parent,
+    metadata: TrackMetadata<'_>,
+    mut handler: H,
+) -> Result<Track<'a>>
+where
+    H: FnMut(&mut [u8]) -> HandlerReturn,
+{
+    let handler: &mut dyn FnMut(&mut [u8]) -> HandlerReturn = &mut handler;
+    let mut handler_return = HandlerReturn::Ok(0);
+
+    let private = &mut (&mut handler_return, handler) as *mut _ as *mut libc::c_void;
+
+    send_track(mtpdev, storage_id, parent, &metadata, |track_t| unsafe {
+        let res = ffi::LIBMTP_Send_Track_From_Handler(
+            mtpdev.inner,
+            Some(data_get_func_handler),
+            private,
+            track_t,
+            None,
+            std::ptr::null(),
+        );
+
+        if res != 0 && !handler_return.is_error() {
+            let _ = mtpdev.latest_error();
+        }
+
+        res != 0 && handler_return.is_error()
+    })
+}
+
+pub(crate) fn send_track_from_handler_with_callback<'a, H, C, R>(
+    mtpdev: &'a MtpDevice,
+    storage_id: u32,
+    parent: Parent,
+    metadata: TrackMetadata<'_>,
+    mut handler: H,
+    mut callback: C,
+) -> Result<Track<'a>>
+where
+    H: FnMut(&mut [u8]) -> HandlerReturn,
+    C: FnMut(u64, u64) -> R,
+    R: Into<CallbackReturn>,
+{
+    let handler: &mut dyn FnMut(&mut [u8]) -> HandlerReturn = &mut handler;
+    let mut handler_return = HandlerReturn::Ok(0);
+
+    let private = &mut (&mut handler_return, handler) as *mut _ as *mut libc::c_void;
+
+    let mut callback = |sent, total| callback(sent, total).into();
+    let mut callback: &mut dyn FnMut(u64, u64) -> CallbackReturn = &mut callback;
+    let callback = &mut callback as *mut _ as *mut libc::c_void as *const _;
+
+    send_track(mtpdev, storage_id, parent, &metadata, |track_t| unsafe {
+        let res = ffi::LIBMTP_Send_Track_From_Handler(
+            mtpdev.inner,
+            Some(data_get_func_handler),
+            private,
+            track_t,
+            Some(progress_func_handler),
+            callback,
+        );
+
+        if res != 0 && !handler_return.is_error() {
+            let _ = mtpdev.latest_error();
+        }
+
+        res != 0 && handler_return.is_error()
+    })
+}
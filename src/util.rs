@@ -2,8 +2,14 @@
 //! (which are not public) and other useful public items.
 
 use libmtp_sys as ffi;
+use std::ffi::{CStr, CString};
+use std::sync::{Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
 
-/// Must return type on callbacks (send and get files)
+use crate::Result;
+
+/// Must return type on callbacks (send and get files), callbacks may also return a `bool` (see
+/// the `From<bool>` implementation).
 #[derive(Debug, Copy, Clone)]
 pub enum CallbackReturn {
     /// Return this to continue the operation.
@@ -12,6 +18,26 @@ pub enum CallbackReturn {
     Cancel,
 }
 
+/// Allows progress callbacks to return a "keep going" `bool`, `true` is
+/// `CallbackReturn::Continue` and `false` is `CallbackReturn::Cancel`.
+///
+/// ## Example
+/// ```
+/// use libmtp_rs::util::CallbackReturn;
+///
+/// let keep_going = true;
+/// assert!(matches!(CallbackReturn::from(keep_going), CallbackReturn::Continue));
+/// ```
+impl From<bool> for CallbackReturn {
+    fn from(keep_going: bool) -> Self {
+        if keep_going {
+            CallbackReturn::Continue
+        } else {
+            CallbackReturn::Cancel
+        }
+    }
+}
+
 #[allow(clippy::transmute_ptr_to_ref)]
 pub(crate) unsafe extern "C" fn progress_func_handler(
     sent: u64,
@@ -49,6 +75,16 @@ impl HandlerReturn {
     }
 }
 
+/// Outcome of a handler driven transfer, tells whether the whole object was transferred or the
+/// handler stopped earlier by returning `HandlerReturn::Cancel`.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum TransferOutcome {
+    /// The whole object was transferred.
+    Completed,
+    /// The handler cancelled the transfer.
+    Cancelled,
+}
+
 #[allow(clippy::transmute_ptr_to_ref)]
 pub(crate) unsafe extern "C" fn data_put_func_handler(
     _params: *mut libc::c_void,
@@ -116,3 +152,484 @@ pub(crate) unsafe extern "C" fn data_get_func_handler(
 
     ret as u16
 }
+
+/// Copies a slice of ids into a `malloc`ed array, so `libmtp` can free it later. Returns a null
+/// pointer if the slice is empty.
+pub(crate) unsafe fn u32_slice_to_c_array(slice: &[u32]) -> *mut u32 {
+    if slice.is_empty() {
+        return std::ptr::null_mut();
+    }
+
+    let size = std::mem::size_of_val(slice);
+    let array = libc::malloc(size) as *mut u32;
+    std::ptr::copy_nonoverlapping(slice.as_ptr(), array, slice.len());
+
+    array
+}
+
+/// Rate limiter used by throttled transfers, keeps the average throughput of a transfer under
+/// `max_bytes_per_sec` by sleeping inside its progress callback.
+pub(crate) struct Throttle {
+    max_bytes_per_sec: u64,
+    start: Instant,
+    last_sent: u64,
+}
+
+impl Throttle {
+    pub(crate) fn new(max_bytes_per_sec: u64) -> Self {
+        Throttle {
+            max_bytes_per_sec,
+            start: Instant::now(),
+            last_sent: 0,
+        }
+    }
+
+    /// Called with the bytes sent so far, sleeps as needed to keep the average throughput under
+    /// the limit, a limit of `0` means no limit.
+    pub(crate) fn wait(&mut self, sent: u64) {
+        if self.max_bytes_per_sec == 0 || sent <= self.last_sent {
+            return;
+        }
+
+        self.last_sent = sent;
+
+        let expected = Duration::from_secs_f64(sent as f64 / self.max_bytes_per_sec as f64);
+        let elapsed = self.start.elapsed();
+
+        if expected > elapsed {
+            std::thread::sleep(expected - elapsed);
+        }
+    }
+}
+
+/// Returns `None` if the string is null or has invalid UTF-8.
+pub(crate) unsafe fn optional_str<'a>(ptr: *const libc::c_char) -> Option<&'a str> {
+    if ptr.is_null() {
+        None
+    } else {
+        CStr::from_ptr(ptr).to_str().ok()
+    }
+}
+
+/// Duplicates the given string with the allocator of libmtp, or returns null if there isn't one.
+pub(crate) unsafe fn optional_strdup(string: Option<&str>) -> Result<*mut libc::c_char> {
+    match string {
+        Some(string) => {
+            let string = CString::new(string)?;
+            Ok(libc::strdup(string.as_ptr()))
+        }
+
+        None => Ok(std::ptr::null_mut()),
+    }
+}
+
+/// Locks a device shared between threads, a request that panicked doesn't leave the device in an
+/// invalid state, so the poisoning is ignored.
+pub(crate) fn lock<T>(device: &Mutex<T>) -> MutexGuard<'_, T> {
+    device.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+#[cfg(feature = "async")]
+pub(crate) use blocking::{channel, spawn_blocking, Receiver};
+
+#[cfg(all(test, feature = "async"))]
+pub(crate) use blocking::block_on;
+
+#[cfg(feature = "async")]
+mod blocking {
+    use std::collections::VecDeque;
+    use std::future::Future;
+    use std::panic::{self, AssertUnwindSafe};
+    use std::pin::Pin;
+    use std::sync::{Arc, Condvar, Mutex, OnceLock, PoisonError};
+    use std::task::{Context, Poll, Waker};
+    use std::thread;
+    use std::time::Duration;
+
+    use super::lock;
+
+    /// Maximum number of threads of the blocking pool, requests to the same device are performed
+    /// one at a time anyway, so more threads would mostly wait for each other.
+    const MAX_THREADS: usize = 8;
+
+    /// How long an idle thread of the blocking pool waits for a new job before exiting.
+    const IDLE_TIMEOUT: Duration = Duration::from_secs(10);
+
+    type Job = Box<dyn FnOnce() + Send>;
+
+    #[derive(Default)]
+    struct PoolState {
+        jobs: VecDeque<Job>,
+        threads: usize,
+        idle: usize,
+    }
+
+    /// Pool of threads running the closures given to `spawn_blocking`, threads are spawned on
+    /// demand (up to `max_threads`) and exit once they have been idle for `idle_timeout`.
+    struct Pool {
+        state: Mutex<PoolState>,
+        available: Condvar,
+        max_threads: usize,
+        idle_timeout: Duration,
+    }
+
+    impl Pool {
+        fn new(max_threads: usize, idle_timeout: Duration) -> Arc<Self> {
+            Arc::new(Pool {
+                state: Mutex::new(PoolState::default()),
+                available: Condvar::new(),
+                max_threads,
+                idle_timeout,
+            })
+        }
+
+        fn global() -> &'static Arc<Pool> {
+            static POOL: OnceLock<Arc<Pool>> = OnceLock::new();
+            POOL.get_or_init(|| Pool::new(MAX_THREADS, IDLE_TIMEOUT))
+        }
+
+        /// Queues the job, waking an idle thread or spawning a new one if there are more queued
+        /// jobs than idle threads and the limit wasn't reached yet.
+        fn execute(self: &Arc<Self>, job: Job) {
+            let mut state = lock(&self.state);
+            state.jobs.push_back(job);
+
+            if state.idle >= state.jobs.len() {
+                self.available.notify_one();
+            } else if state.threads < self.max_threads {
+                state.threads += 1;
+
+                let pool = Arc::clone(self);
+                thread::Builder::new()
+                    .name("libmtp-rs-blocking".to_string())
+                    .spawn(move || pool.work())
+                    .expect("failed to spawn a blocking thread");
+            }
+        }
+
+        fn work(&self) {
+            let mut state = lock(&self.state);
+
+            loop {
+                if let Some(job) = state.jobs.pop_front() {
+                    drop(state);
+                    job();
+                    state = lock(&self.state);
+                    continue;
+                }
+
+                state.idle += 1;
+                let (guard, wait) = self
+                    .available
+                    .wait_timeout(state, self.idle_timeout)
+                    .unwrap_or_else(PoisonError::into_inner);
+                state = guard;
+                state.idle -= 1;
+
+                if wait.timed_out() && state.jobs.is_empty() {
+                    state.threads -= 1;
+                    return;
+                }
+            }
+        }
+    }
+
+    struct Shared<T> {
+        result: Option<std::thread::Result<T>>,
+        waker: Option<Waker>,
+    }
+
+    /// Future that resolves once the closure given to `spawn_blocking` returns, if the closure
+    /// panics the panic is propagated to the task awaiting it.
+    pub(crate) struct BlockingFuture<T> {
+        shared: Arc<Mutex<Shared<T>>>,
+    }
+
+    impl<T> Future for BlockingFuture<T> {
+        type Output = T;
+
+        fn poll(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<T> {
+            let mut shared = lock(&self.shared);
+
+            match shared.result.take() {
+                Some(Ok(value)) => Poll::Ready(value),
+                Some(Err(payload)) => panic::resume_unwind(payload),
+                None => {
+                    shared.waker = Some(cx.waker().clone());
+                    Poll::Pending
+                }
+            }
+        }
+    }
+
+    /// Runs `f` in a shared pool of threads, so blocking `libmtp` calls don't block the async
+    /// executor. The closure starts right away, even if the future isn't awaited yet.
+    pub(crate) fn spawn_blocking<F, T>(f: F) -> BlockingFuture<T>
+    where
+        F: FnOnce() -> T + Send + 'static,
+        T: Send + 'static,
+    {
+        let shared = Arc::new(Mutex::new(Shared {
+            result: None,
+            waker: None,
+        }));
+
+        let job_shared = Arc::clone(&shared);
+        Pool::global().execute(Box::new(move || {
+            let result = panic::catch_unwind(AssertUnwindSafe(f));
+
+            let mut shared = lock(&job_shared);
+            shared.result = Some(result);
+
+            if let Some(waker) = shared.waker.take() {
+                waker.wake();
+            }
+        }));
+
+        BlockingFuture { shared }
+    }
+
+    #[derive(Debug)]
+    struct Queue<T> {
+        values: VecDeque<T>,
+        closed: bool,
+        waker: Option<Waker>,
+    }
+
+    /// Sending half of `channel`, the channel is closed once it's dropped.
+    #[derive(Debug)]
+    pub(crate) struct Sender<T> {
+        queue: Arc<Mutex<Queue<T>>>,
+    }
+
+    impl<T> Sender<T> {
+        /// Queues the value, it's dropped right away if the receiver is gone.
+        pub(crate) fn send(&self, value: T) {
+            if Arc::strong_count(&self.queue) == 1 {
+                return;
+            }
+
+            let mut queue = lock(&self.queue);
+            queue.values.push_back(value);
+
+            if let Some(waker) = queue.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+
+    impl<T> Drop for Sender<T> {
+        fn drop(&mut self) {
+            let mut queue = lock(&self.queue);
+            queue.closed = true;
+
+            if let Some(waker) = queue.waker.take() {
+                waker.wake();
+            }
+        }
+    }
+
+    /// Receiving half of `channel`, waiting for values wakes the task instead of blocking the
+    /// thread.
+    #[derive(Debug)]
+    pub(crate) struct Receiver<T> {
+        queue: Arc<Mutex<Queue<T>>>,
+    }
+
+    impl<T> Receiver<T> {
+        pub(crate) fn poll_recv(&mut self, cx: &mut Context<'_>) -> Poll<Option<T>> {
+            let mut queue = lock(&self.queue);
+
+            match queue.values.pop_front() {
+                Some(value) => Poll::Ready(Some(value)),
+                None if queue.closed => Poll::Ready(None),
+                None => {
+                    queue.waker = Some(cx.waker().clone());
+                    Poll::Pending
+                }
+            }
+        }
+
+        pub(crate) fn try_recv(&mut self) -> Option<T> {
+            lock(&self.queue).values.pop_front()
+        }
+    }
+
+    /// Unbounded single producer channel whose receiver can be awaited, used to forward the
+    /// progress of blocking transfers to async tasks.
+    pub(crate) fn channel<T>() -> (Sender<T>, Receiver<T>) {
+        let queue = Arc::new(Mutex::new(Queue {
+            values: VecDeque::new(),
+            closed: false,
+            waker: None,
+        }));
+
+        let sender = Sender {
+            queue: Arc::clone(&queue),
+        };
+
+        (sender, Receiver { queue })
+    }
+
+    /// Minimal executor for the tests, parks the thread until the future is woken.
+    #[cfg(test)]
+    pub(crate) fn block_on<F: Future>(future: F) -> F::Output {
+        use std::task::Wake;
+
+        struct ThreadWaker(thread::Thread);
+
+        impl Wake for ThreadWaker {
+            fn wake(self: Arc<Self>) {
+                self.0.unpark();
+            }
+        }
+
+        let mut future = Box::pin(future);
+        let waker = Arc::new(ThreadWaker(thread::current())).into();
+        let mut cx = Context::from_waker(&waker);
+
+        loop {
+            match future.as_mut().poll(&mut cx) {
+                Poll::Ready(output) => return output,
+                Poll::Pending => thread::park(),
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use std::sync::mpsc;
+        use std::time::Instant;
+
+        /// Waits until the state of the pool satisfies `cond`, panics after a few seconds.
+        fn wait_for(pool: &Pool, cond: impl Fn(&PoolState) -> bool) {
+            let start = Instant::now();
+
+            while !cond(&lock(&pool.state)) {
+                assert!(start.elapsed() < Duration::from_secs(5), "timed out");
+                thread::sleep(Duration::from_millis(1));
+            }
+        }
+
+        #[test]
+        fn spawn_blocking_returns_the_result() {
+            assert_eq!(block_on(spawn_blocking(|| 2 + 2)), 4);
+        }
+
+        #[test]
+        fn spawn_blocking_propagates_panics() {
+            let res = panic::catch_unwind(|| block_on(spawn_blocking(|| panic!("boom"))));
+            assert!(res.is_err());
+        }
+
+        #[test]
+        fn pool_reuses_idle_threads() {
+            let pool = Pool::new(2, Duration::from_secs(10));
+            let (sender, receiver) = mpsc::channel();
+
+            let first = sender.clone();
+            pool.execute(Box::new(move || {
+                first.send(thread::current().id()).unwrap()
+            }));
+            let first = receiver.recv().unwrap();
+            wait_for(&pool, |state| state.idle == 1);
+
+            pool.execute(Box::new(move || {
+                sender.send(thread::current().id()).unwrap()
+            }));
+            assert_eq!(receiver.recv().unwrap(), first);
+            assert_eq!(lock(&pool.state).threads, 1);
+        }
+
+        #[test]
+        fn pool_limits_its_threads() {
+            let pool = Pool::new(2, Duration::from_secs(10));
+            let (sender, receiver) = mpsc::channel();
+
+            for i in 0..6 {
+                let sender = sender.clone();
+                pool.execute(Box::new(move || {
+                    thread::sleep(Duration::from_millis(10));
+                    sender.send(i).unwrap();
+                }));
+            }
+
+            assert!(lock(&pool.state).threads <= 2);
+
+            let mut done: Vec<_> = receiver.iter().take(6).collect();
+            done.sort_unstable();
+            assert_eq!(done, [0, 1, 2, 3, 4, 5]);
+        }
+
+        #[test]
+        fn idle_threads_exit() {
+            let pool = Pool::new(1, Duration::from_millis(10));
+            let (sender, receiver) = mpsc::channel();
+
+            pool.execute(Box::new(move || sender.send(()).unwrap()));
+            receiver.recv().unwrap();
+            wait_for(&pool, |state| state.threads == 0);
+        }
+
+        #[test]
+        fn channel_delivers_values_in_order() {
+            let (sender, mut receiver) = channel();
+            sender.send(1);
+            sender.send(2);
+            drop(sender);
+
+            assert_eq!(receiver.try_recv(), Some(1));
+            assert_eq!(
+                block_on(std::future::poll_fn(|cx| receiver.poll_recv(cx))),
+                Some(2)
+            );
+            assert_eq!(
+                block_on(std::future::poll_fn(|cx| receiver.poll_recv(cx))),
+                None
+            );
+        }
+
+        #[test]
+        fn channel_wakes_the_receiver() {
+            let (sender, mut receiver) = channel();
+
+            let thread = thread::spawn(move || {
+                thread::sleep(Duration::from_millis(10));
+                sender.send(1);
+            });
+
+            assert_eq!(
+                block_on(std::future::poll_fn(|cx| receiver.poll_recv(cx))),
+                Some(1)
+            );
+            assert_eq!(
+                block_on(std::future::poll_fn(|cx| receiver.poll_recv(cx))),
+                None
+            );
+            thread.join().unwrap();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Error;
+
+    #[test]
+    fn strdup_rejects_nul_bytes() {
+        let res = unsafe { optional_strdup(Some("bad\0name")) };
+        assert!(matches!(res, Err(Error::InvalidString { .. })));
+    }
+
+    #[test]
+    fn strdup_copies_strings() {
+        let ptr = unsafe { optional_strdup(Some("name")) }.unwrap();
+        assert_eq!(unsafe { optional_str(ptr) }, Some("name"));
+        unsafe { libc::free(ptr as *mut _) };
+
+        let ptr = unsafe { optional_strdup(None) }.unwrap();
+        assert!(ptr.is_null());
+    }
+}
@@ -4,9 +4,11 @@
 use libmtp_sys as ffi;
 use num_derive::FromPrimitive;
 use num_traits::FromPrimitive;
+use std::convert::TryFrom;
+use std::fmt::{self, Display};
 
 /// Enumeration to determine the data type of the allowed values.
-#[derive(Debug, Clone, Copy, FromPrimitive)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, FromPrimitive)]
 pub enum DataType {
     I8 = 0,
     U8,
@@ -18,6 +20,21 @@ pub enum DataType {
     U64,
 }
 
+/// Value of an object property, read with the getter that matches the data type of the property
+/// (see `Object::get_property_value`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PropertyValue {
+    I8(i8),
+    U8(u8),
+    I16(i16),
+    U16(u16),
+    I32(i32),
+    U32(u32),
+    I64(i64),
+    U64(u64),
+    String(String),
+}
+
 /// Contains relevant information about the allowed values for an specific type `T`.
 #[derive(Debug, Clone)]
 pub struct Values<T: Copy> {
@@ -45,6 +62,54 @@ impl<T: Copy> Values<T> {
     }
 }
 
+/// Maximum number of values generated by [`Values::iter`](struct.Values.html#method.iter) for
+/// ranges, longer ranges are truncated.
+pub const MAX_RANGE_VALUES: usize = 10_000;
+
+impl<T> Values<T>
+where
+    T: Copy + Into<i128> + TryFrom<i128>,
+{
+    /// Iterates over the allowed values, for enumerations these are the values of `vals`, for
+    /// ranges these are generated from `min` to `max` (included) by `step`, up to
+    /// `MAX_RANGE_VALUES` values. This way both kinds of allowed values can be listed uniformly
+    /// (e.g. in a dropdown).
+    pub fn iter(&self) -> impl Iterator<Item = T> + '_ {
+        let (min, step, count) = self.range();
+
+        let range = (0..count).filter_map(move |i| T::try_from(min + step * i as i128).ok());
+        self.vals.iter().copied().chain(range)
+    }
+
+    /// Returns the number of values yielded by [`iter`](#method.iter).
+    pub fn len(&self) -> usize {
+        self.vals.len() + self.range().2
+    }
+
+    /// Whether there are no allowed values at all.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the start, step and number of values of the range, the number is `0` for
+    /// enumerations.
+    fn range(&self) -> (i128, i128, usize) {
+        let min = self.min.into();
+        let max = self.max.into();
+        let step = self.step.into();
+
+        let count = if !self.vals.is_empty() || max < min {
+            0
+        } else if step <= 0 {
+            1
+        } else {
+            ((max - min) / step + 1).min(MAX_RANGE_VALUES as i128) as usize
+        };
+
+        (min, step, count)
+    }
+}
+
 /// Contains the allowed values of an specific attribute, determines which data type
 /// should be used, and if the values are a range or enumeration.
 #[derive(Debug, Clone)]
@@ -121,6 +186,92 @@ impl AllowedValues {
     }
 }
 
+impl AllowedValues {
+    /// Returns the enumerated values formatted as strings, if the allowed values are a range
+    /// this returns an empty vector.
+    pub fn enumerated_as_strings(&self) -> Vec<String> {
+        if self.is_range {
+            Vec::new()
+        } else {
+            self.formatted()
+                .map(|(_, _, _, vals)| vals)
+                .unwrap_or_default()
+        }
+    }
+
+    /// Returns the minimum, maximum and step of the range formatted as strings, if the allowed
+    /// values aren't a range this returns `None`.
+    pub fn range_as_tuple(&self) -> Option<(String, String, String)> {
+        if self.is_range {
+            self.formatted().map(|(min, max, step, _)| (min, max, step))
+        } else {
+            None
+        }
+    }
+
+    /// Returns the values of whichever data type is present widened into `i128` (which can hold
+    /// all of them), so they can be used without matching on the data type. The typed getters
+    /// (like [`u16_values`](#method.u16_values)) are still there when the type matters.
+    pub fn values_i128(&self) -> Option<Values<i128>> {
+        match self.datatype {
+            DataType::I8 => self.i8_values.as_ref().map(widen_values),
+            DataType::U8 => self.u8_values.as_ref().map(widen_values),
+            DataType::I16 => self.i16_values.as_ref().map(widen_values),
+            DataType::U16 => self.u16_values.as_ref().map(widen_values),
+            DataType::I32 => self.i32_values.as_ref().map(widen_values),
+            DataType::U32 => self.u32_values.as_ref().map(widen_values),
+            DataType::I64 => self.i64_values.as_ref().map(widen_values),
+            DataType::U64 => self.u64_values.as_ref().map(widen_values),
+        }
+    }
+
+    /// Formats the minimum, maximum, step and values of the present data type.
+    fn formatted(&self) -> Option<FormattedValues> {
+        match self.datatype {
+            DataType::I8 => self.i8_values.as_ref().map(format_values),
+            DataType::U8 => self.u8_values.as_ref().map(format_values),
+            DataType::I16 => self.i16_values.as_ref().map(format_values),
+            DataType::U16 => self.u16_values.as_ref().map(format_values),
+            DataType::I32 => self.i32_values.as_ref().map(format_values),
+            DataType::U32 => self.u32_values.as_ref().map(format_values),
+            DataType::I64 => self.i64_values.as_ref().map(format_values),
+            DataType::U64 => self.u64_values.as_ref().map(format_values),
+        }
+    }
+}
+
+fn widen_values<T: Copy + Into<i128>>(values: &Values<T>) -> Values<i128> {
+    Values {
+        max: values.max.into(),
+        min: values.min.into(),
+        step: values.step.into(),
+        vals: values.vals.iter().map(|&val| val.into()).collect(),
+    }
+}
+
+/// Minimum, maximum, step and values formatted as strings.
+type FormattedValues = (String, String, String, Vec<String>);
+
+fn format_values<T: Copy + Display>(values: &Values<T>) -> FormattedValues {
+    (
+        values.min.to_string(),
+        values.max.to_string(),
+        values.step.to_string(),
+        values.vals.iter().map(ToString::to_string).collect(),
+    )
+}
+
+/// Formats ranges as `range [min..=max step step]` and enumerations as `{a, b, c}`.
+impl Display for AllowedValues {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some((min, max, step)) = self.range_as_tuple() {
+            write!(f, "range [{}..={} step {}]", min, max, step)
+        } else {
+            write!(f, "{{{}}}", self.enumerated_as_strings().join(", "))
+        }
+    }
+}
+
 impl Default for AllowedValues {
     fn default() -> Self {
         AllowedValues {
@@ -250,3 +401,115 @@ impl AllowedValues {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn values<T: Copy>(min: T, max: T, step: T, vals: Vec<T>) -> Values<T> {
+        Values {
+            max,
+            min,
+            step,
+            vals,
+        }
+    }
+
+    #[test]
+    fn displays_ranges() {
+        let allowed = AllowedValues {
+            u8_values: Some(values(0, 100, 1, Vec::new())),
+            datatype: DataType::U8,
+            is_range: true,
+            ..Default::default()
+        };
+
+        assert_eq!(allowed.to_string(), "range [0..=100 step 1]");
+        assert_eq!(
+            allowed.range_as_tuple(),
+            Some(("0".to_string(), "100".to_string(), "1".to_string()))
+        );
+        assert!(allowed.enumerated_as_strings().is_empty());
+    }
+
+    #[test]
+    fn displays_enumerations() {
+        let allowed = AllowedValues {
+            u16_values: Some(values(0, 0, 0, vec![1, 2, 3])),
+            datatype: DataType::U16,
+            is_range: false,
+            ..Default::default()
+        };
+
+        assert_eq!(allowed.to_string(), "{1, 2, 3}");
+        assert_eq!(allowed.enumerated_as_strings(), ["1", "2", "3"]);
+        assert_eq!(allowed.range_as_tuple(), None);
+    }
+
+    #[test]
+    fn widens_u32_values() {
+        let allowed = AllowedValues {
+            u32_values: Some(values(10, u32::MAX, 5, vec![10, 15, u32::MAX])),
+            datatype: DataType::U32,
+            is_range: false,
+            ..Default::default()
+        };
+
+        let widened = allowed.values_i128().unwrap();
+        assert_eq!(widened.min(), 10);
+        assert_eq!(widened.max(), u32::MAX as i128);
+        assert_eq!(widened.step(), 5);
+        assert_eq!(widened.vals(), [10, 15, u32::MAX as i128]);
+    }
+
+    #[test]
+    fn no_widened_values_without_typed_values() {
+        let allowed = AllowedValues {
+            datatype: DataType::U32,
+            ..Default::default()
+        };
+
+        assert!(allowed.values_i128().is_none());
+    }
+
+    #[test]
+    fn iterates_enumerations() {
+        let enumeration = values(0u8, 0, 0, vec![4, 8, 15]);
+
+        assert_eq!(enumeration.iter().collect::<Vec<_>>(), [4, 8, 15]);
+        assert_eq!(enumeration.len(), 3);
+    }
+
+    #[test]
+    fn iterates_ranges() {
+        let range = values(-4i16, 6, 5, Vec::new());
+
+        assert_eq!(range.iter().collect::<Vec<_>>(), [-4, 1, 6]);
+        assert_eq!(range.len(), 3);
+    }
+
+    #[test]
+    fn range_with_zero_step_only_has_min() {
+        let range = values(7u32, 100, 0, Vec::new());
+
+        assert_eq!(range.iter().collect::<Vec<_>>(), [7]);
+        assert_eq!(range.len(), 1);
+    }
+
+    #[test]
+    fn range_with_max_below_min_is_empty() {
+        let range = values(10i32, -10, 1, Vec::new());
+
+        assert_eq!(range.iter().count(), 0);
+        assert!(range.is_empty());
+    }
+
+    #[test]
+    fn long_ranges_are_truncated() {
+        let range = values(0u64, u64::MAX, 1, Vec::new());
+
+        assert_eq!(range.len(), MAX_RANGE_VALUES);
+        assert_eq!(range.iter().count(), MAX_RANGE_VALUES);
+        assert_eq!(range.iter().last(), Some(MAX_RANGE_VALUES as u64 - 1));
+    }
+}